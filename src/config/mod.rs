@@ -0,0 +1,225 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use crate::drawing::viewport::ViewportLayout;
+use crate::presentation::fullscreen::{FullscreenMode, VideoModeRequest};
+use crate::presentation::present_mode::PresentModeSetting;
+use crate::texture::mipmap::MipmapFilter;
+use crate::texture::sampler::SamplerDesc;
+
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+pub const MAX_FRAMES_IN_FLIGHT: usize = 8;
+pub const DEFAULT_BENCH_WARMUP_FRAMES: u32 = 100;
+
+/// Settings chosen at startup from the command line.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// How many frames the CPU may record ahead of the GPU.
+    pub frames_in_flight: usize,
+    /// Where to write a Chrome trace of CPU and GPU timings on exit.
+    pub trace_path: Option<PathBuf>,
+    /// Query and log pipeline statistics of the scene's draws.
+    pub pipeline_statistics: bool,
+    pub present_mode: PresentModeSetting,
+    /// Prefer HDR and wide-gamut swapchain formats over SDR ones.
+    pub hdr: bool,
+    /// The mode to start in, and the fullscreen mode the toggle switches to if not windowed.
+    pub fullscreen: FullscreenMode,
+    /// Index of the monitor to go fullscreen on, the window's current one if unset.
+    pub monitor: Option<usize>,
+    /// The video mode for exclusive fullscreen, the monitor's largest if unset.
+    pub video_mode: Option<VideoModeRequest>,
+    /// How the scene window is split into viewports.
+    pub viewport_layout: ViewportLayout,
+    /// Open a second window previewing the material of the first scene object.
+    pub material_preview: bool,
+    /// A cubemap to draw behind the scene, see `texture::cubemap::create_cubemap`.
+    pub skybox: Option<PathBuf>,
+    /// How the scene's material samples its texture.
+    pub sampler: SamplerDesc,
+    /// How mips are generated for textures that don't come with them.
+    pub mipmap_filter: MipmapFilter,
+    /// How many frames to measure in benchmark mode, which exits once they are rendered.
+    pub bench_frames: Option<u32>,
+    /// Frames rendered before measuring, to let caches and clocks settle.
+    pub bench_warmup_frames: u32,
+    /// Where to also write the benchmark report as JSON.
+    pub bench_json_path: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            trace_path: None,
+            pipeline_statistics: false,
+            present_mode: PresentModeSetting::default(),
+            hdr: false,
+            fullscreen: FullscreenMode::Windowed,
+            monitor: None,
+            video_mode: None,
+            viewport_layout: ViewportLayout::default(),
+            material_preview: false,
+            skybox: None,
+            sampler: SamplerDesc::default(),
+            mipmap_filter: MipmapFilter::default(),
+            bench_frames: None,
+            bench_warmup_frames: DEFAULT_BENCH_WARMUP_FRAMES,
+            bench_json_path: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parses `--flag value` or `--flag=value` style arguments.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("Missing value for `{}`.", flag))
+            };
+
+            // Flags that take no value reject `--flag=value` rather than ignoring the value.
+            let switch = || match &inline {
+                Some(_) => Err(anyhow!("`{}` doesn't take a value.", flag)),
+                None => Ok(true),
+            };
+
+            match flag.as_str() {
+                "--frames-in-flight" => {
+                    let frames = value()?.parse::<usize>()?;
+                    if !(1..=MAX_FRAMES_IN_FLIGHT).contains(&frames) {
+                        return Err(anyhow!(
+                            "`--frames-in-flight` must be between 1 and {}.",
+                            MAX_FRAMES_IN_FLIGHT
+                        ));
+                    }
+                    config.frames_in_flight = frames;
+                }
+                "--trace" => config.trace_path = Some(value()?.into()),
+                "--pipeline-statistics" => config.pipeline_statistics = switch()?,
+                "--hdr" => config.hdr = switch()?,
+                "--fullscreen" => config.fullscreen = FullscreenMode::parse(&value()?)?,
+                "--monitor" => config.monitor = Some(value()?.parse()?),
+                "--video-mode" => config.video_mode = Some(VideoModeRequest::parse(&value()?)?),
+                "--viewports" => config.viewport_layout = ViewportLayout::parse(&value()?)?,
+                "--material-preview" => config.material_preview = switch()?,
+                "--skybox" => config.skybox = Some(value()?.into()),
+                "--sampler" => config.sampler = SamplerDesc::parse(&value()?)?,
+                "--mipmaps" => config.mipmap_filter = MipmapFilter::parse(&value()?)?,
+                "--present-mode" => config.present_mode = PresentModeSetting::parse(&value()?)?,
+                "--bench" => config.bench_frames = Some(value()?.parse()?),
+                "--bench-warmup" => config.bench_warmup_frames = value()?.parse()?,
+                "--bench-json" => config.bench_json_path = Some(value()?.into()),
+                _ => return Err(anyhow!("Unknown argument `{}`.", flag)),
+            }
+        }
+
+        if config.bench_json_path.is_some() && config.bench_frames.is_none() {
+            return Err(anyhow!("`--bench-json` requires `--bench`."));
+        }
+
+        if config.material_preview && config.bench_frames.is_some() {
+            return Err(anyhow!(
+                "`--material-preview` can't be used with `--bench`."
+            ));
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config> {
+        Config::from_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_separate_and_inline_values() {
+        let config = parse(&["--frames-in-flight", "3", "--trace=trace.json"]).unwrap();
+        assert_eq!(config.frames_in_flight, 3);
+        assert_eq!(config.trace_path, Some(PathBuf::from("trace.json")));
+
+        let config = parse(&["--frames-in-flight=4", "--trace", "trace.json"]).unwrap();
+        assert_eq!(config.frames_in_flight, 4);
+        assert_eq!(config.trace_path, Some(PathBuf::from("trace.json")));
+    }
+
+    #[test]
+    fn test_missing_value() {
+        assert!(parse(&["--trace"]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_argument() {
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--unknown=1"]).is_err());
+        assert!(parse(&["scene.obj"]).is_err());
+    }
+
+    #[test]
+    fn test_frames_in_flight_range() {
+        assert!(parse(&["--frames-in-flight", "0"]).is_err());
+        assert!(parse(&["--frames-in-flight", "-1"]).is_err());
+        assert!(parse(&["--frames-in-flight", "two"]).is_err());
+
+        let max = MAX_FRAMES_IN_FLIGHT.to_string();
+        assert_eq!(
+            parse(&["--frames-in-flight", &max])
+                .unwrap()
+                .frames_in_flight,
+            MAX_FRAMES_IN_FLIGHT
+        );
+
+        let over = (MAX_FRAMES_IN_FLIGHT + 1).to_string();
+        assert!(parse(&["--frames-in-flight", &over]).is_err());
+    }
+
+    #[test]
+    fn test_switch_with_value() {
+        assert!(parse(&["--hdr"]).unwrap().hdr);
+        assert!(
+            parse(&["--pipeline-statistics"])
+                .unwrap()
+                .pipeline_statistics
+        );
+        assert!(parse(&["--pipeline-statistics=true"]).is_err());
+        assert!(parse(&["--hdr=false"]).is_err());
+        assert!(parse(&["--hdr="]).is_err());
+        assert!(parse(&["--material-preview=0"]).is_err());
+    }
+
+    #[test]
+    fn test_bench_flags() {
+        let config = parse(&["--bench", "10", "--bench-warmup=5"]).unwrap();
+        assert_eq!(config.bench_frames, Some(10));
+        assert_eq!(config.bench_warmup_frames, 5);
+        assert_eq!(config.bench_json_path, None);
+
+        assert!(parse(&["--bench", "ten"]).is_err());
+        assert!(parse(&["--bench-json", "bench.json"]).is_err());
+        assert!(parse(&["--bench", "10", "--bench-json", "bench.json"]).is_ok());
+    }
+
+    #[test]
+    fn test_material_preview() {
+        assert!(parse(&["--material-preview"]).unwrap().material_preview);
+        assert!(!parse(&[]).unwrap().material_preview);
+        assert!(parse(&["--material-preview", "--bench", "10"]).is_err());
+        assert!(parse(&["--bench=10", "--material-preview"]).is_err());
+    }
+}
@@ -0,0 +1,578 @@
+use std::mem::size_of;
+use std::ptr::copy_nonoverlapping as memcpy;
+
+use anyhow::Result;
+use cgmath::{vec4, InnerSpace, Matrix};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::drawing::viewport::MAX_VIEWPORTS;
+use crate::pipeline::compute;
+use crate::uniform::descriptor;
+use crate::vertex::vertex;
+use crate::AppData;
+
+type Vec4 = cgmath::Vector4<f32>;
+type Mat4 = cgmath::Matrix4<f32>;
+
+const SHADER_CULL_COMP: &[u8] = include_bytes!("../vertex/shaders/cull_comp.spv");
+
+const WORKGROUP_SIZE: u32 = 64;
+const INITIAL_OBJECT_CAPACITY: usize = 1024;
+
+/// An instance to be tested against the frustum, matching `CullObject` in `cull.comp`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CullObject {
+    model: Mat4,
+    tint: Vec4,
+    /// World-space center in xyz, radius in w.
+    sphere: Vec4,
+    /// The indirect draw of the object's material.
+    draw: u32,
+    _padding: [u32; 3],
+}
+
+/// The objects of one material, drawn by one indirect command.
+///
+/// The command's instances start at `first_object` in the visible buffer, so the ranges of
+/// different materials never overlap.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MaterialDraw {
+    pub material_index: u32,
+    pub first_object: u32,
+    pub object_count: u32,
+}
+
+/// The frustum of every viewport, matching `Frusta` in `cull.comp`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct Frusta {
+    planes: [[Vec4; 6]; MAX_VIEWPORTS],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CullPushConstants {
+    object_count: u32,
+    frustum_count: u32,
+}
+
+pub unsafe fn create_culling_pipeline(device: &Device, data: &mut AppData) -> Result<()> {
+    let mut bindings = (0..3)
+        .map(|i| {
+            vk::DescriptorSetLayoutBinding::builder()
+                .binding(i)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        })
+        .collect::<Vec<_>>();
+
+    bindings.push(
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(3)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::COMPUTE),
+    );
+
+    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+    data.culling_data.descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
+
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        .offset(0)
+        .size(size_of::<CullPushConstants>() as u32);
+
+    let set_layouts = &[data.culling_data.descriptor_set_layout];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+
+    data.culling_data.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+    data.culling_data.pipeline = compute::create_compute_pipeline(
+        device,
+        data.culling_data.pipeline_layout,
+        SHADER_CULL_COMP,
+    )?;
+
+    info!("Culling pipeline created.");
+    Ok(())
+}
+
+pub unsafe fn create_culling_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let frame_count = data.drawing_data.frames.len();
+
+    let storage_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(3 * frame_count as u32);
+
+    let uniform_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count(frame_count as u32);
+
+    let pool_sizes = &[storage_size, uniform_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+        .pool_sizes(pool_sizes)
+        .max_sets(frame_count as u32);
+
+    data.culling_data.descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    let layouts = vec![data.culling_data.descriptor_set_layout; frame_count];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(data.culling_data.descriptor_pool)
+        .set_layouts(&layouts);
+
+    data.culling_data.descriptor_sets = device.allocate_descriptor_sets(&info)?;
+
+    data.culling_data.capacities = vec![0; frame_count];
+    data.culling_data.object_buffers = vec![vk::Buffer::null(); frame_count];
+    data.culling_data.object_buffers_memory = vec![vk::DeviceMemory::null(); frame_count];
+    data.culling_data.visible_buffers = vec![vk::Buffer::null(); frame_count];
+    data.culling_data.visible_buffers_memory = vec![vk::DeviceMemory::null(); frame_count];
+    data.culling_data.indirect_buffers = vec![vk::Buffer::null(); frame_count];
+    data.culling_data.indirect_buffers_memory = vec![vk::DeviceMemory::null(); frame_count];
+    data.culling_data.object_counts = vec![0; frame_count];
+    data.culling_data.draws = vec![vec![]; frame_count];
+
+    data.culling_data.frustum_buffers.clear();
+    data.culling_data.frustum_buffers_memory.clear();
+
+    for i in 0..frame_count {
+        let (frustum_buffer, frustum_buffer_memory) = vertex::create_buffer(
+            instance,
+            device,
+            data,
+            size_of::<Frusta>() as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        data.culling_data.frustum_buffers.push(frustum_buffer);
+        data.culling_data
+            .frustum_buffers_memory
+            .push(frustum_buffer_memory);
+
+        let info = vk::DescriptorBufferInfo::builder()
+            .buffer(frustum_buffer)
+            .offset(0)
+            .range(size_of::<Frusta>() as u64);
+
+        let buffer_info = &[info];
+        let frustum_write = vk::WriteDescriptorSet::builder()
+            .dst_set(data.culling_data.descriptor_sets[i])
+            .dst_binding(3)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .buffer_info(buffer_info);
+
+        device.update_descriptor_sets(&[frustum_write], &[] as &[vk::CopyDescriptorSet]);
+
+        resize_culling_buffers(instance, device, data, i, INITIAL_OBJECT_CAPACITY)?;
+    }
+
+    Ok(())
+}
+
+unsafe fn resize_culling_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+    capacity: usize,
+) -> Result<()> {
+    destroy_object_buffers(device, data, frame);
+
+    let (object_buffer, object_buffer_memory) = vertex::create_buffer(
+        instance,
+        device,
+        data,
+        (size_of::<CullObject>() * capacity) as u64,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+
+    let (visible_buffer, visible_buffer_memory) = vertex::create_buffer(
+        instance,
+        device,
+        data,
+        (size_of::<crate::vertex::instance::InstanceData>() * capacity) as u64,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    // Every material has at least one object, so there are never more draws than objects.
+    let (indirect_buffer, indirect_buffer_memory) = vertex::create_buffer(
+        instance,
+        device,
+        data,
+        (size_of::<vk::DrawIndexedIndirectCommand>() * capacity) as u64,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+
+    data.culling_data.capacities[frame] = capacity;
+    data.culling_data.object_buffers[frame] = object_buffer;
+    data.culling_data.object_buffers_memory[frame] = object_buffer_memory;
+    data.culling_data.visible_buffers[frame] = visible_buffer;
+    data.culling_data.visible_buffers_memory[frame] = visible_buffer_memory;
+    data.culling_data.indirect_buffers[frame] = indirect_buffer;
+    data.culling_data.indirect_buffers_memory[frame] = indirect_buffer_memory;
+
+    let buffers = [object_buffer, visible_buffer, indirect_buffer];
+
+    let buffer_infos = buffers
+        .iter()
+        .map(|b| {
+            [vk::DescriptorBufferInfo::builder()
+                .buffer(*b)
+                .offset(0)
+                .range(vk::WHOLE_SIZE as u64)
+                .build()]
+        })
+        .collect::<Vec<_>>();
+
+    let writes = buffer_infos
+        .iter()
+        .enumerate()
+        .map(|(binding, info)| {
+            vk::WriteDescriptorSet::builder()
+                .dst_set(data.culling_data.descriptor_sets[frame])
+                .dst_binding(binding as u32)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(info)
+        })
+        .collect::<Vec<_>>();
+
+    device.update_descriptor_sets(&writes, &[] as &[vk::CopyDescriptorSet]);
+
+    Ok(())
+}
+
+/// Uploads this frame's objects and viewport frusta and resets the indirect draw commands the
+/// compute pass fills, one per material.
+///
+/// The visible count left in the commands by this frame's last submission is read back
+/// first; the frame's fence has been waited on, so this never stalls.
+pub unsafe fn update_culling_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+) -> Result<()> {
+    let draw_count = data.culling_data.draws[frame].len();
+    if draw_count > 0 {
+        let command_memory = data.culling_data.indirect_buffers_memory[frame];
+        let command_size = (size_of::<vk::DrawIndexedIndirectCommand>() * draw_count) as u64;
+
+        let memory =
+            device.map_memory(command_memory, 0, command_size, vk::MemoryMapFlags::empty())?;
+        let commands =
+            std::slice::from_raw_parts(memory.cast::<vk::DrawIndexedIndirectCommand>(), draw_count);
+
+        let visible_count = commands.iter().map(|c| c.instance_count).sum::<u32>();
+        if visible_count != data.culling_data.visible_count {
+            debug!(
+                "GPU culling: {} of {} objects visible.",
+                visible_count, data.culling_data.object_counts[frame]
+            );
+        }
+        data.culling_data.visible_count = visible_count;
+
+        device.unmap_memory(command_memory);
+    }
+
+    let center = vec4(
+        data.vertex_data.bounds_center[0],
+        data.vertex_data.bounds_center[1],
+        data.vertex_data.bounds_center[2],
+        1.0,
+    );
+
+    // Objects are grouped by material so that each material is a single indirect draw.
+    let radius = data.vertex_data.bounds_radius;
+    let draw_list = &data.drawing_data.draw_list;
+    let mut items = draw_list.items.iter().collect::<Vec<_>>();
+    items.sort_by_key(|i| i.material_index);
+
+    let materials = items
+        .iter()
+        .flat_map(|item| (0..item.instance_count).map(|_| item.material_index))
+        .collect::<Vec<_>>();
+    let draws = get_material_draws(&materials);
+
+    let objects = items
+        .iter()
+        .flat_map(|item| draw_list.world_instances(item))
+        .zip(
+            draws
+                .iter()
+                .enumerate()
+                .flat_map(|(d, draw)| (0..draw.object_count).map(move |_| d as u32)),
+        )
+        .map(|(i, draw)| {
+            let scale = (0..3)
+                .map(|c| i.model[c].truncate().magnitude())
+                .fold(0.0, f32::max);
+            let mut sphere = i.model * center;
+            sphere.w = radius * scale;
+            CullObject {
+                model: i.model,
+                tint: i.tint,
+                sphere,
+                draw,
+                _padding: [0; 3],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if objects.len() > data.culling_data.capacities[frame] {
+        let capacity = objects.len().next_power_of_two();
+        resize_culling_buffers(instance, device, data, frame, capacity)?;
+    }
+
+    data.culling_data.object_counts[frame] = objects.len() as u32;
+
+    if !draws.is_empty() {
+        let commands = draws
+            .iter()
+            .map(|d| vk::DrawIndexedIndirectCommand {
+                index_count: data.vertex_data.indices.len() as u32,
+                instance_count: 0,
+                first_index: 0,
+                vertex_offset: 0,
+                first_instance: d.first_object,
+            })
+            .collect::<Vec<_>>();
+
+        let memory = data.culling_data.indirect_buffers_memory[frame];
+        let size = (size_of::<vk::DrawIndexedIndirectCommand>() * commands.len()) as u64;
+
+        let destination = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+
+        memcpy(commands.as_ptr(), destination.cast(), commands.len());
+
+        device.unmap_memory(memory);
+    }
+
+    data.culling_data.draws[frame] = draws;
+
+    let mut frusta = Frusta {
+        planes: [[Vec4::new(0.0, 0.0, 0.0, 0.0); 6]; MAX_VIEWPORTS],
+    };
+    for (planes, viewport) in frusta.planes.iter_mut().zip(&data.drawing_data.viewports) {
+        let (view, proj) = descriptor::get_view_proj(&viewport.camera, viewport.rect.extent);
+        *planes = get_frustum_planes(proj * view);
+    }
+
+    let memory = data.culling_data.frustum_buffers_memory[frame];
+    let destination = device.map_memory(
+        memory,
+        0,
+        size_of::<Frusta>() as u64,
+        vk::MemoryMapFlags::empty(),
+    )?;
+
+    memcpy(&frusta, destination.cast(), 1);
+
+    device.unmap_memory(memory);
+
+    if objects.is_empty() {
+        return Ok(());
+    }
+
+    let memory = data.culling_data.object_buffers_memory[frame];
+    let size = (size_of::<CullObject>() * objects.len()) as u64;
+
+    let destination = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+
+    memcpy(objects.as_ptr(), destination.cast(), objects.len());
+
+    device.unmap_memory(memory);
+
+    Ok(())
+}
+
+/// Splits objects sorted by material into one draw per material.
+fn get_material_draws(materials: &[u32]) -> Vec<MaterialDraw> {
+    let mut draws = Vec::<MaterialDraw>::new();
+
+    for (i, material_index) in materials.iter().enumerate() {
+        match draws.last_mut() {
+            Some(draw) if draw.material_index == *material_index => draw.object_count += 1,
+            _ => draws.push(MaterialDraw {
+                material_index: *material_index,
+                first_object: i as u32,
+                object_count: 1,
+            }),
+        }
+    }
+
+    draws
+}
+
+/// Whether this frame's draws are culled on the GPU.
+///
+/// With several viewports, objects are kept if they are inside any of their frusta.
+pub fn culling_active(data: &AppData) -> bool {
+    data.culling_data.enabled
+}
+
+/// Records the culling dispatch; must be called outside of a render pass.
+pub unsafe fn record_culling(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+) {
+    let object_count = data.culling_data.object_counts[frame];
+
+    let push_constants = CullPushConstants {
+        object_count,
+        frustum_count: data.drawing_data.viewports.len() as u32,
+    };
+
+    device.cmd_bind_pipeline(
+        command_buffer,
+        vk::PipelineBindPoint::COMPUTE,
+        data.culling_data.pipeline,
+    );
+
+    device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::COMPUTE,
+        data.culling_data.pipeline_layout,
+        0,
+        &[data.culling_data.descriptor_sets[frame]],
+        &[],
+    );
+
+    device.cmd_push_constants(
+        command_buffer,
+        data.culling_data.pipeline_layout,
+        vk::ShaderStageFlags::COMPUTE,
+        0,
+        std::slice::from_raw_parts(
+            &push_constants as *const CullPushConstants as *const u8,
+            size_of::<CullPushConstants>(),
+        ),
+    );
+
+    device.cmd_dispatch(command_buffer, object_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+
+    let barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(
+            vk::AccessFlags::INDIRECT_COMMAND_READ | vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+        );
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::VERTEX_INPUT,
+        vk::DependencyFlags::empty(),
+        &[barrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[] as &[vk::ImageMemoryBarrier],
+    );
+}
+
+/// Extracts the six inward-facing frustum planes from a view-projection matrix.
+///
+/// Vulkan clip space has a depth range of `[0, 1]`, so the near plane is just the third row.
+fn get_frustum_planes(view_proj: Mat4) -> [Vec4; 6] {
+    let m = view_proj.transpose();
+    let (r0, r1, r2, r3) = (m.x, m.y, m.z, m.w);
+
+    [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|p| p / p.truncate().magnitude())
+}
+
+unsafe fn destroy_object_buffers(device: &Device, data: &AppData, frame: usize) {
+    device.destroy_buffer(data.culling_data.object_buffers[frame], None);
+    device.free_memory(data.culling_data.object_buffers_memory[frame], None);
+    device.destroy_buffer(data.culling_data.visible_buffers[frame], None);
+    device.free_memory(data.culling_data.visible_buffers_memory[frame], None);
+    device.destroy_buffer(data.culling_data.indirect_buffers[frame], None);
+    device.free_memory(data.culling_data.indirect_buffers_memory[frame], None);
+}
+
+/// Destroys the buffers and frees the descriptor set of a frame nothing renders anymore.
+pub unsafe fn release_culling_buffers(
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+) -> Result<()> {
+    destroy_object_buffers(device, data, frame);
+
+    let culling = &mut data.culling_data;
+    device.destroy_buffer(culling.frustum_buffers[frame], None);
+    device.free_memory(culling.frustum_buffers_memory[frame], None);
+    device.free_descriptor_sets(culling.descriptor_pool, &[culling.descriptor_sets[frame]])?;
+
+    culling.object_buffers[frame] = vk::Buffer::null();
+    culling.object_buffers_memory[frame] = vk::DeviceMemory::null();
+    culling.visible_buffers[frame] = vk::Buffer::null();
+    culling.visible_buffers_memory[frame] = vk::DeviceMemory::null();
+    culling.indirect_buffers[frame] = vk::Buffer::null();
+    culling.indirect_buffers_memory[frame] = vk::DeviceMemory::null();
+    culling.frustum_buffers[frame] = vk::Buffer::null();
+    culling.frustum_buffers_memory[frame] = vk::DeviceMemory::null();
+    culling.descriptor_sets[frame] = vk::DescriptorSet::null();
+    culling.capacities[frame] = 0;
+    culling.object_counts[frame] = 0;
+    culling.draws[frame].clear();
+
+    Ok(())
+}
+
+pub unsafe fn destroy_culling_buffers(device: &Device, data: &AppData) {
+    for i in 0..data.culling_data.indirect_buffers.len() {
+        destroy_object_buffers(device, data, i);
+        device.destroy_buffer(data.culling_data.frustum_buffers[i], None);
+        device.free_memory(data.culling_data.frustum_buffers_memory[i], None);
+    }
+
+    device.destroy_descriptor_pool(data.culling_data.descriptor_pool, None);
+}
+
+pub unsafe fn destroy_culling_pipeline(device: &Device, data: &AppData) {
+    device.destroy_pipeline(data.culling_data.pipeline, None);
+    device.destroy_pipeline_layout(data.culling_data.pipeline_layout, None);
+    device.destroy_descriptor_set_layout(data.culling_data.descriptor_set_layout, None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_material_draws() {
+        let materials = [0, 0, 1, 3, 3, 3, 7];
+        let draws = get_material_draws(&materials);
+
+        assert_eq!(draws.len(), 4);
+        assert_eq!(draws[0].first_object, 0);
+
+        // The ranges are contiguous, cover every object and hold a single material each.
+        let mut next = 0;
+        for draw in &draws {
+            assert_eq!(draw.first_object, next);
+            let range =
+                draw.first_object as usize..(draw.first_object + draw.object_count) as usize;
+            assert!(materials[range].iter().all(|m| *m == draw.material_index));
+            next += draw.object_count;
+        }
+        assert_eq!(next as usize, materials.len());
+
+        assert!(get_material_draws(&[]).is_empty());
+    }
+}
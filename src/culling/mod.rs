@@ -0,0 +1,37 @@
+use vulkanalia::prelude::v1_0::*;
+
+use crate::scene::ObjectId;
+
+pub mod frustum;
+pub mod occlusion;
+
+#[derive(Clone, Debug, Default)]
+pub struct CullingData {
+    pub enabled: bool,
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: vk::Pipeline,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub descriptor_sets: Vec<vk::DescriptorSet>,
+    pub capacities: Vec<usize>,
+    pub object_buffers: Vec<vk::Buffer>,
+    pub object_buffers_memory: Vec<vk::DeviceMemory>,
+    pub visible_buffers: Vec<vk::Buffer>,
+    pub visible_buffers_memory: Vec<vk::DeviceMemory>,
+    pub indirect_buffers: Vec<vk::Buffer>,
+    pub indirect_buffers_memory: Vec<vk::DeviceMemory>,
+    /// The frustum of each viewport, which objects are tested against.
+    pub frustum_buffers: Vec<vk::Buffer>,
+    pub frustum_buffers_memory: Vec<vk::DeviceMemory>,
+    pub object_counts: Vec<u32>,
+    /// The indirect draw of each material in a frame's objects, in indirect buffer order.
+    pub draws: Vec<Vec<frustum::MaterialDraw>>,
+    pub visible_count: u32,
+    pub occlusion_enabled: bool,
+    /// Why occlusion queries are skipped despite being enabled, warned about once per change.
+    pub occlusion_blocked: Option<&'static str>,
+    pub occlusion_query_pools: Vec<vk::QueryPool>,
+    pub occlusion_capacities: Vec<u32>,
+    /// The object each occlusion query of a frame's last submission belongs to.
+    pub occlusion_objects: Vec<Vec<ObjectId>>,
+}
@@ -0,0 +1,247 @@
+use anyhow::Result;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::culling::frustum;
+use crate::AppData;
+
+/// How many frames an occluded object is left out of the draw list before it is drawn and
+/// queried again.
+const RETEST_FRAMES: u32 = 8;
+const INITIAL_QUERY_CAPACITY: u32 = 64;
+
+pub unsafe fn create_occlusion_query_pools(device: &Device, data: &mut AppData) -> Result<()> {
+    let frame_count = data.drawing_data.frames.len();
+
+    data.culling_data.occlusion_query_pools = vec![vk::QueryPool::null(); frame_count];
+    data.culling_data.occlusion_capacities = vec![0; frame_count];
+    data.culling_data.occlusion_objects = vec![vec![]; frame_count];
+
+    for i in 0..frame_count {
+        resize_occlusion_query_pool(device, data, i, INITIAL_QUERY_CAPACITY)?;
+    }
+
+    Ok(())
+}
+
+unsafe fn resize_occlusion_query_pool(
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+    capacity: u32,
+) -> Result<()> {
+    device.destroy_query_pool(data.culling_data.occlusion_query_pools[frame], None);
+
+    let info = vk::QueryPoolCreateInfo::builder()
+        .query_type(vk::QueryType::OCCLUSION)
+        .query_count(capacity);
+
+    data.culling_data.occlusion_query_pools[frame] = device.create_query_pool(&info, None)?;
+    data.culling_data.occlusion_capacities[frame] = capacity;
+
+    Ok(())
+}
+
+/// Counts another frame for the objects left out of the draw list, letting those left out for
+/// `RETEST_FRAMES` back in so they are queried again.
+///
+/// Called once per frame, however many windows render it.
+pub fn advance_occluded_objects(data: &mut AppData) {
+    let objects = &mut data.scene_data.objects;
+
+    if !data.culling_data.occlusion_enabled {
+        objects.iter_mut().for_each(|o| o.occluded_frames = 0);
+        return;
+    }
+
+    for object in objects.iter_mut().filter(|o| o.occluded_frames > 0) {
+        object.occluded_frames = (object.occluded_frames + 1) % (RETEST_FRAMES + 1);
+    }
+}
+
+/// Marks objects hidden by this frame's previous occlusion queries, so the next draw list
+/// leaves them out.
+///
+/// Called after the frame's fence has been waited on.
+pub unsafe fn update_occluded_objects(
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+) -> Result<()> {
+    if !data.culling_data.occlusion_enabled {
+        data.culling_data.occlusion_objects[frame].clear();
+        return Ok(());
+    }
+
+    let queried = &data.culling_data.occlusion_objects[frame];
+    if queried.is_empty() {
+        return Ok(());
+    }
+
+    // Each result is the sample count followed by its availability.
+    let mut results = vec![0u64; queried.len() * 2];
+    device.get_query_pool_results(
+        data.culling_data.occlusion_query_pools[frame],
+        0,
+        queried.len() as u32,
+        std::slice::from_raw_parts_mut(
+            results.as_mut_ptr().cast::<u8>(),
+            results.len() * std::mem::size_of::<u64>(),
+        ),
+        2 * std::mem::size_of::<u64>() as u64,
+        vk::QueryResultFlags::_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+    )?;
+
+    let mut occluded = 0;
+    for (id, result) in queried.iter().zip(results.chunks_exact(2)) {
+        let (samples, available) = (result[0], result[1]);
+        if available == 0 {
+            continue;
+        }
+
+        if let Some(object) = data.scene_data.get_mut(*id) {
+            object.occluded_frames = if samples == 0 { 1 } else { 0 };
+            occluded += (samples == 0) as usize;
+        }
+    }
+
+    if occluded > 0 {
+        debug!(
+            "Occlusion queries: {} of {} objects hidden.",
+            occluded,
+            queried.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Sizes this frame's query pool for the draw list and remembers which object each query
+/// belongs to.
+pub unsafe fn prepare_occlusion_queries(
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+) -> Result<()> {
+    data.culling_data.occlusion_objects[frame].clear();
+
+    if !data.culling_data.occlusion_enabled {
+        data.culling_data.occlusion_blocked = None;
+        return Ok(());
+    }
+
+    // The indirect path issues a single draw for every object, so there is nothing to query,
+    // and with several viewports each object is drawn more than once.
+    let blocked = if frustum::culling_active(data) {
+        Some("GPU culling is enabled")
+    } else if data.drawing_data.viewports.len() > 1 {
+        Some("there are several viewports")
+    } else {
+        None
+    };
+
+    if blocked != data.culling_data.occlusion_blocked {
+        if let Some(reason) = blocked {
+            warn!(
+                "Occlusion culling is enabled but inactive, since {}.",
+                reason
+            );
+        }
+        data.culling_data.occlusion_blocked = blocked;
+    }
+
+    if blocked.is_some() {
+        return Ok(());
+    }
+
+    let count = data.drawing_data.draw_list.items.len() as u32;
+    if count > data.culling_data.occlusion_capacities[frame] {
+        resize_occlusion_query_pool(device, data, frame, count.next_power_of_two())?;
+    }
+
+    data.culling_data.occlusion_objects[frame] = data
+        .drawing_data
+        .draw_list
+        .items
+        .iter()
+        .map(|i| i.object)
+        .collect();
+
+    Ok(())
+}
+
+/// Whether draws recorded for this frame should be wrapped in occlusion queries.
+pub fn occlusion_queries_active(data: &AppData, frame: usize) -> bool {
+    !data.culling_data.occlusion_objects[frame].is_empty()
+}
+
+/// Resets this frame's queries; must be recorded outside a render pass.
+pub unsafe fn reset_occlusion_queries(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+) {
+    if occlusion_queries_active(data, frame) {
+        device.cmd_reset_query_pool(
+            command_buffer,
+            data.culling_data.occlusion_query_pools[frame],
+            0,
+            data.culling_data.occlusion_objects[frame].len() as u32,
+        );
+    }
+}
+
+/// Begins the occlusion query of the draw item at `index`.
+///
+/// Draws are tested against the depth of whatever was drawn before them.
+pub unsafe fn begin_occlusion_query(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    index: usize,
+) {
+    let flags = if data.setup_data.features.occlusion_query_precise == vk::TRUE {
+        vk::QueryControlFlags::PRECISE
+    } else {
+        vk::QueryControlFlags::empty()
+    };
+
+    device.cmd_begin_query(
+        command_buffer,
+        data.culling_data.occlusion_query_pools[frame],
+        index as u32,
+        flags,
+    );
+}
+
+pub unsafe fn end_occlusion_query(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    index: usize,
+) {
+    device.cmd_end_query(
+        command_buffer,
+        data.culling_data.occlusion_query_pools[frame],
+        index as u32,
+    );
+}
+
+/// Destroys the query pool of a frame nothing renders anymore.
+pub unsafe fn release_occlusion_query_pool(device: &Device, data: &mut AppData, frame: usize) {
+    let culling = &mut data.culling_data;
+    device.destroy_query_pool(culling.occlusion_query_pools[frame], None);
+    culling.occlusion_query_pools[frame] = vk::QueryPool::null();
+    culling.occlusion_capacities[frame] = 0;
+    culling.occlusion_objects[frame].clear();
+}
+
+pub unsafe fn destroy_occlusion_query_pools(device: &Device, data: &AppData) {
+    data.culling_data
+        .occlusion_query_pools
+        .iter()
+        .for_each(|p| device.destroy_query_pool(*p, None));
+}
@@ -1,9 +1,36 @@
-use anyhow::Result;
+use std::mem::size_of;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use cgmath::SquareMatrix;
 use vulkanalia::prelude::v1_0::*;
 
+use crate::culling::{frustum, occlusion};
+use crate::drawing::draw_list::DrawItem;
+use crate::drawing::frame::FrameContext;
+use crate::drawing::skybox;
+use crate::profiling::statistics;
+use crate::profiling::timestamp::{self, GpuPass};
 use crate::setup::device::queue_families;
+use crate::texture::material;
 use crate::AppData;
 
+type Mat4 = cgmath::Matrix4<f32>;
+
+const MAX_WORKERS: usize = 8;
+/// Below this many draws per thread, spawning threads costs more than it saves.
+const MIN_DRAWS_PER_WORKER: usize = 256;
+
+/// Per-draw data pushed before each `cmd_draw_indexed`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PushConstants {
+    pub model: Mat4,
+    pub material_index: u32,
+    /// Which of the uniform buffer's view and projection matrices to use.
+    pub viewport_index: u32,
+}
+
 pub unsafe fn create_command_pool(
     instance: &Instance,
     device: &Device,
@@ -18,6 +45,31 @@ pub unsafe fn create_command_pool(
 
     data.drawing_data.command_pool = device.create_command_pool(&info, None)?;
 
+    // One pool per frame in flight, reset as a whole once that frame's fence has signaled.
+    info = vk::CommandPoolCreateInfo::builder()
+        .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+        .queue_family_index(indices.graphics);
+
+    // Command pools are externally synchronized, so every recording thread gets its own.
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_WORKERS);
+
+    // Every window has its own frames in flight, one after the other.
+    let frame_count = data.config.frames_in_flight * data.windows.len();
+    data.drawing_data.frames = (0..frame_count)
+        .map(|_| {
+            Ok(FrameContext {
+                command_pool: device.create_command_pool(&info, None)?,
+                secondary_command_pools: (0..workers)
+                    .map(|_| device.create_command_pool(&info, None))
+                    .collect::<Result<Vec<_>, _>>()?,
+                ..Default::default()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     info = vk::CommandPoolCreateInfo::builder()
         .flags(vk::CommandPoolCreateFlags::TRANSIENT) // Optional.
         .queue_family_index(indices.transfer);
@@ -28,83 +80,414 @@ pub unsafe fn create_command_pool(
 }
 
 pub unsafe fn create_command_buffers(device: &Device, data: &mut AppData) -> Result<()> {
-    let allocate_info = vk::CommandBufferAllocateInfo::builder()
-        .command_pool(data.drawing_data.command_pool)
-        .level(vk::CommandBufferLevel::PRIMARY)
-        .command_buffer_count(data.drawing_data.framebuffers.len() as u32);
+    for frame in &mut data.drawing_data.frames {
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(frame.command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
 
-    data.drawing_data.command_buffers = device.allocate_command_buffers(&allocate_info)?;
+        frame.command_buffer = device.allocate_command_buffers(&allocate_info)?[0];
 
-    for (i, command_buffer) in data.drawing_data.command_buffers.iter().enumerate() {
-        let inheritance = vk::CommandBufferInheritanceInfo::builder();
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(frame.command_pool)
+            .level(vk::CommandBufferLevel::SECONDARY)
+            .command_buffer_count(1);
 
-        let info = vk::CommandBufferBeginInfo::builder()
-            .flags(vk::CommandBufferUsageFlags::empty()) // Optional.
-            .inheritance_info(&inheritance); // Optional.
+        frame.skybox_command_buffer = device.allocate_command_buffers(&allocate_info)?[0];
 
-        device.begin_command_buffer(*command_buffer, &info)?;
+        frame.secondary_command_buffers = frame
+            .secondary_command_pools
+            .iter()
+            .map(|p| {
+                let allocate_info = vk::CommandBufferAllocateInfo::builder()
+                    .command_pool(*p)
+                    .level(vk::CommandBufferLevel::SECONDARY)
+                    .command_buffer_count(1);
 
-        let render_area = vk::Rect2D::builder()
-            .offset(vk::Offset2D::default())
-            .extent(data.presentation_data.swapchain_extent);
+                Ok(device.allocate_command_buffers(&allocate_info)?[0])
+            })
+            .collect::<Result<Vec<_>>>()?;
+    }
 
-        let color_clear_value = vk::ClearValue {
-            color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0],
-            },
-        };
+    Ok(())
+}
 
-        let depth_clear_value = vk::ClearValue {
-            depth_stencil: vk::ClearDepthStencilValue {
-                depth: 1.0,
-                stencil: 0,
-            },
-        };
+/// Records this frame's command buffer from the draw list, targeting the acquired image.
+///
+/// The draw list is split across worker threads, each recording a secondary command buffer
+/// from its own command pool; the primary only runs culling and executes the secondaries.
+pub unsafe fn record_command_buffer(
+    device: &Device,
+    data: &AppData,
+    frame: usize,
+    image_index: usize,
+) -> Result<()> {
+    let context = &data.drawing_data.frames[frame];
+    let command_buffer = context.command_buffer;
 
-        let clear_values = &[color_clear_value, depth_clear_value];
-        let info = vk::RenderPassBeginInfo::builder()
-            .render_pass(data.pipeline_data.render_pass)
-            .framebuffer(data.drawing_data.framebuffers[i])
-            .render_area(render_area)
-            .clear_values(clear_values);
+    device.reset_command_pool(context.command_pool, vk::CommandPoolResetFlags::empty())?;
 
-        device.cmd_begin_render_pass(*command_buffer, &info, vk::SubpassContents::INLINE);
+    let info =
+        vk::CommandBufferBeginInfo::builder().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
 
-        device.cmd_bind_pipeline(
-            *command_buffer,
-            vk::PipelineBindPoint::GRAPHICS,
-            data.pipeline_data.pipeline,
-        );
+    device.begin_command_buffer(command_buffer, &info)?;
 
-        device.cmd_bind_vertex_buffers(*command_buffer, 0, &[data.vertex_data.vertex_buffer], &[0]);
-        device.cmd_bind_index_buffer(
-            *command_buffer,
-            data.vertex_data.index_buffer,
-            0,
-            vk::IndexType::UINT32,
-        );
+    timestamp::reset_queries(device, data, command_buffer, frame);
+    statistics::reset_statistics_queries(device, data, command_buffer, frame);
+    occlusion::reset_occlusion_queries(device, data, command_buffer, frame);
+    timestamp::begin_pass(device, data, command_buffer, frame, GpuPass::Frame);
+
+    timestamp::begin_pass(device, data, command_buffer, frame, GpuPass::Culling);
+    if frustum::culling_active(data) {
+        frustum::record_culling(device, data, command_buffer, frame);
+    }
+    timestamp::end_pass(device, data, command_buffer, frame, GpuPass::Culling);
+
+    let render_area = vk::Rect2D::builder()
+        .offset(vk::Offset2D::default())
+        .extent(data.presentation_data.swapchain_extent);
+
+    let color_clear_value = vk::ClearValue {
+        color: vk::ClearColorValue {
+            float32: [0.0, 0.0, 0.0, 1.0],
+        },
+    };
+
+    let depth_clear_value = vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        },
+    };
+
+    let clear_values = &[color_clear_value, depth_clear_value];
+    let info = vk::RenderPassBeginInfo::builder()
+        .render_pass(data.pipeline_data.render_pass)
+        .framebuffer(data.drawing_data.framebuffers[image_index])
+        .render_area(render_area)
+        .clear_values(clear_values);
+
+    // Timestamps can't be written between secondary command buffers, so the main pass is
+    // measured around the whole render pass.
+    timestamp::begin_pass(device, data, command_buffer, frame, GpuPass::MainPass);
+
+    device.cmd_begin_render_pass(
+        command_buffer,
+        &info,
+        vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+    );
+
+    let mut secondary_command_buffers =
+        record_secondary_command_buffers(device, data, frame, image_index)?;
+
+    // The skybox only fills what the draw list left at the far plane.
+    if data.texture_data.skybox.is_some() {
+        secondary_command_buffers.push(skybox::record_skybox(device, data, frame, image_index)?);
+    }
+
+    device.cmd_execute_commands(command_buffer, &secondary_command_buffers);
+
+    device.cmd_end_render_pass(command_buffer);
+    timestamp::end_pass(device, data, command_buffer, frame, GpuPass::MainPass);
+
+    timestamp::end_pass(device, data, command_buffer, frame, GpuPass::Frame);
+    device.end_command_buffer(command_buffer)?;
+
+    Ok(())
+}
+
+/// Records the draw list in parallel and returns the secondary command buffers to execute.
+unsafe fn record_secondary_command_buffers(
+    device: &Device,
+    data: &AppData,
+    frame: usize,
+    image_index: usize,
+) -> Result<Vec<vk::CommandBuffer>> {
+    let context = &data.drawing_data.frames[frame];
+
+    // The indirect draws are one command per material, so they get a single secondary command
+    // buffer.
+    if frustum::culling_active(data) {
+        record_secondary_command_buffer(device, data, frame, 0, image_index, 0, &[])?;
+        return Ok(vec![context.secondary_command_buffers[0]]);
+    }
+
+    let items = &data.drawing_data.draw_list.items;
+    let workers = context.secondary_command_buffers.len();
+    let chunk_size = items
+        .len()
+        .div_ceil(workers)
+        .max(MIN_DRAWS_PER_WORKER)
+        .max(1);
+    let chunks = items.chunks(chunk_size).collect::<Vec<_>>();
+
+    if chunks.len() <= 1 {
+        let chunk = chunks.first().copied().unwrap_or(&[]);
+        record_secondary_command_buffer(device, data, frame, 0, image_index, 0, chunk)?;
+        return Ok(vec![context.secondary_command_buffers[0]]);
+    }
+
+    thread::scope(|scope| {
+        let handles = chunks
+            .iter()
+            .enumerate()
+            .map(|(worker, chunk)| {
+                scope.spawn(move || {
+                    record_secondary_command_buffer(
+                        device,
+                        data,
+                        frame,
+                        worker,
+                        image_index,
+                        worker * chunk_size,
+                        chunk,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles.into_iter().try_for_each(|h| {
+            h.join()
+                .map_err(|_| anyhow!("Recording thread panicked."))?
+        })
+    })?;
+
+    Ok(context.secondary_command_buffers[..chunks.len()].to_vec())
+}
 
+/// Inheritance info for secondary command buffers continuing the main render pass.
+pub fn get_inheritance_info(
+    data: &AppData,
+    image_index: usize,
+) -> vk::CommandBufferInheritanceInfoBuilder<'static> {
+    vk::CommandBufferInheritanceInfo::builder()
+        .render_pass(data.pipeline_data.render_pass)
+        .subpass(0)
+        .framebuffer(data.drawing_data.framebuffers[image_index])
+}
+
+unsafe fn record_secondary_command_buffer(
+    device: &Device,
+    data: &AppData,
+    frame: usize,
+    worker: usize,
+    image_index: usize,
+    first_item: usize,
+    items: &[DrawItem],
+) -> Result<()> {
+    let context = &data.drawing_data.frames[frame];
+    let command_buffer = context.secondary_command_buffers[worker];
+
+    device.reset_command_pool(
+        context.secondary_command_pools[worker],
+        vk::CommandPoolResetFlags::empty(),
+    )?;
+
+    let inheritance = get_inheritance_info(data, image_index);
+
+    let info = vk::CommandBufferBeginInfo::builder()
+        .flags(
+            vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+                | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+        )
+        .inheritance_info(&inheritance);
+
+    device.begin_command_buffer(command_buffer, &info)?;
+
+    statistics::begin_statistics_query(device, data, command_buffer, frame, worker);
+
+    device.cmd_bind_pipeline(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        data.pipeline_data.pipeline,
+    );
+
+    // With culling, the compute pass writes the visible instances in world space.
+    let instance_buffer = if frustum::culling_active(data) {
+        data.culling_data.visible_buffers[frame]
+    } else {
+        data.vertex_data.instance_buffers[frame]
+    };
+
+    device.cmd_bind_vertex_buffers(
+        command_buffer,
+        0,
+        &[data.vertex_data.vertex_buffer, instance_buffer],
+        &[0, 0],
+    );
+    device.cmd_bind_index_buffer(
+        command_buffer,
+        data.vertex_data.index_buffer,
+        0,
+        vk::IndexType::UINT32,
+    );
+
+    device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        data.pipeline_data.pipeline_layout,
+        0,
+        &[context.descriptor_set],
+        &[],
+    );
+
+    for (index, viewport) in data.drawing_data.viewports.iter().enumerate() {
+        set_viewport(device, command_buffer, viewport.rect);
+
+        if frustum::culling_active(data) {
+            record_indirect_draw(device, data, command_buffer, frame, index);
+        } else {
+            record_draws(
+                device,
+                data,
+                command_buffer,
+                frame,
+                index,
+                first_item,
+                items,
+            );
+        }
+    }
+
+    statistics::end_statistics_query(device, data, command_buffer, frame, worker);
+
+    device.end_command_buffer(command_buffer)?;
+
+    Ok(())
+}
+
+/// Sets the viewport and scissor to `rect`.
+///
+/// Dynamic state is not inherited from the primary command buffer, so every secondary sets it.
+pub unsafe fn set_viewport(device: &Device, command_buffer: vk::CommandBuffer, rect: vk::Rect2D) {
+    let viewport = vk::Viewport::builder()
+        .x(rect.offset.x as f32)
+        .y(rect.offset.y as f32)
+        .width(rect.extent.width as f32)
+        .height(rect.extent.height as f32)
+        .min_depth(0.0)
+        .max_depth(1.0);
+
+    device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+    device.cmd_set_scissor(command_buffer, 0, &[rect]);
+}
+
+unsafe fn record_indirect_draw(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    viewport_index: usize,
+) {
+    // The compute pass fills one indirect command per material, in draw order.
+    let stride = size_of::<vk::DrawIndexedIndirectCommand>();
+
+    for (i, draw) in data.culling_data.draws[frame].iter().enumerate() {
         device.cmd_bind_descriptor_sets(
-            *command_buffer,
+            command_buffer,
             vk::PipelineBindPoint::GRAPHICS,
             data.pipeline_data.pipeline_layout,
-            0,
-            &[data.uniform_data.descriptor_sets[i]],
+            1,
+            &[material::get_material_descriptor_set(
+                data,
+                draw.material_index,
+            )],
             &[],
         );
 
+        push_constants(
+            device,
+            data,
+            command_buffer,
+            &PushConstants {
+                model: Mat4::identity(),
+                material_index: draw.material_index,
+                viewport_index: viewport_index as u32,
+            },
+        );
+
+        device.cmd_draw_indexed_indirect(
+            command_buffer,
+            data.culling_data.indirect_buffers[frame],
+            (i * stride) as u64,
+            1,
+            stride as u32,
+        );
+    }
+}
+
+unsafe fn record_draws(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    viewport_index: usize,
+    first_item: usize,
+    items: &[DrawItem],
+) {
+    let queries = occlusion::occlusion_queries_active(data, frame);
+    let mut bound_material = None;
+
+    for (i, item) in items.iter().enumerate() {
+        if bound_material != Some(item.material_index) {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                data.pipeline_data.pipeline_layout,
+                1,
+                &[material::get_material_descriptor_set(
+                    data,
+                    item.material_index,
+                )],
+                &[],
+            );
+            bound_material = Some(item.material_index);
+        }
+
+        if queries {
+            occlusion::begin_occlusion_query(device, data, command_buffer, frame, first_item + i);
+        }
+
+        push_constants(
+            device,
+            data,
+            command_buffer,
+            &PushConstants {
+                model: item.model,
+                material_index: item.material_index,
+                viewport_index: viewport_index as u32,
+            },
+        );
+
         device.cmd_draw_indexed(
-            *command_buffer,
+            command_buffer,
             data.vertex_data.indices.len() as u32,
-            1,
-            0,
+            item.instance_count,
             0,
             0,
+            item.first_instance,
         );
 
-        device.cmd_end_render_pass(*command_buffer);
-        device.end_command_buffer(*command_buffer)?;
+        if queries {
+            occlusion::end_occlusion_query(device, data, command_buffer, frame, first_item + i);
+        }
     }
+}
 
-    Ok(())
+pub unsafe fn push_constants(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    push_constants: &PushConstants,
+) {
+    device.cmd_push_constants(
+        command_buffer,
+        data.pipeline_data.pipeline_layout,
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+        0,
+        std::slice::from_raw_parts(
+            push_constants as *const PushConstants as *const u8,
+            size_of::<PushConstants>(),
+        ),
+    );
 }
@@ -0,0 +1,63 @@
+use crate::scene::{ObjectId, SceneData};
+use crate::vertex::instance::InstanceData;
+
+type Mat4 = cgmath::Matrix4<f32>;
+
+/// One indexed, instanced draw of the loaded model.
+#[derive(Copy, Clone, Debug)]
+pub struct DrawItem {
+    pub object: ObjectId,
+    pub model: Mat4,
+    pub material_index: u32,
+    pub first_instance: u32,
+    pub instance_count: u32,
+}
+
+/// The draws of a single frame, rebuilt from the scene before recording.
+#[derive(Clone, Debug, Default)]
+pub struct DrawList {
+    pub items: Vec<DrawItem>,
+    /// The instances of every item, contiguous and in item order.
+    pub instances: Vec<InstanceData>,
+}
+
+impl DrawList {
+    pub fn build(scene: &SceneData, time: f32) -> Self {
+        let mut draw_list = Self::default();
+
+        for object in scene
+            .objects
+            .iter()
+            .filter(|o| o.visible && o.occluded_frames == 0)
+        {
+            if object.instances.is_empty() {
+                continue;
+            }
+
+            draw_list.items.push(DrawItem {
+                object: object.id,
+                model: object.model(time),
+                material_index: object.material_index,
+                first_instance: draw_list.instances.len() as u32,
+                instance_count: object.instances.len() as u32,
+            });
+
+            draw_list.instances.extend_from_slice(&object.instances);
+        }
+
+        draw_list
+    }
+
+    /// The instances of an item, in world space.
+    pub fn world_instances<'a>(
+        &'a self,
+        item: &'a DrawItem,
+    ) -> impl Iterator<Item = InstanceData> + 'a {
+        let start = item.first_instance as usize;
+        let end = start + item.instance_count as usize;
+        self.instances[start..end].iter().map(|i| InstanceData {
+            model: item.model * i.model,
+            tint: i.tint,
+        })
+    }
+}
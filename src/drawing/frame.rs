@@ -0,0 +1,81 @@
+use std::ops::Range;
+
+use anyhow::Result;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::culling::{frustum, occlusion};
+use crate::profiling::{statistics, timestamp};
+use crate::AppData;
+
+/// The resources one frame in flight records and submits with.
+///
+/// These are reused once the frame's fence has signaled and, unlike per-image resources,
+/// survive swapchain recreation.
+#[derive(Clone, Debug, Default)]
+pub struct FrameContext {
+    pub command_pool: vk::CommandPool,
+    pub command_buffer: vk::CommandBuffer,
+    pub secondary_command_pools: Vec<vk::CommandPool>,
+    pub secondary_command_buffers: Vec<vk::CommandBuffer>,
+    /// Recorded on the main thread after the draw list, so it comes from `command_pool`.
+    pub skybox_command_buffer: vk::CommandBuffer,
+    pub uniform_buffer: vk::Buffer,
+    pub uniform_buffer_memory: vk::DeviceMemory,
+    pub descriptor_set: vk::DescriptorSet,
+    pub image_available_semaphore: vk::Semaphore,
+    pub render_finished_semaphore: vk::Semaphore,
+    pub in_flight_fence: vk::Fence,
+}
+
+unsafe fn destroy_frame(device: &Device, frame: &FrameContext) {
+    device.destroy_fence(frame.in_flight_fence, None);
+    device.destroy_semaphore(frame.render_finished_semaphore, None);
+    device.destroy_semaphore(frame.image_available_semaphore, None);
+
+    device.destroy_buffer(frame.uniform_buffer, None);
+    device.free_memory(frame.uniform_buffer_memory, None);
+
+    frame
+        .secondary_command_pools
+        .iter()
+        .for_each(|p| device.destroy_command_pool(*p, None));
+    device.destroy_command_pool(frame.command_pool, None);
+}
+
+/// Destroys the resources of frames nothing renders anymore, like those of a closed window.
+///
+/// The frames keep their slots with null handles, so the indices of the other frames stay
+/// valid and destroying them again at exit does nothing.
+pub unsafe fn release_frames(
+    device: &Device,
+    data: &mut AppData,
+    frames: Range<usize>,
+) -> Result<()> {
+    for i in frames {
+        let frame = &data.drawing_data.frames[i];
+        destroy_frame(device, frame);
+        device.free_descriptor_sets(data.uniform_data.descriptor_pool, &[frame.descriptor_set])?;
+        data.drawing_data.frames[i] = FrameContext::default();
+
+        frustum::release_culling_buffers(device, data, i)?;
+        occlusion::release_occlusion_query_pool(device, data, i);
+        timestamp::release_timestamp_query_pool(device, data, i);
+        statistics::release_statistics_query_pool(device, data, i);
+
+        let vertex = &mut data.vertex_data;
+        device.destroy_buffer(vertex.instance_buffers[i], None);
+        device.free_memory(vertex.instance_buffers_memory[i], None);
+        vertex.instance_buffers[i] = vk::Buffer::null();
+        vertex.instance_buffers_memory[i] = vk::DeviceMemory::null();
+        vertex.instance_capacities[i] = 0;
+    }
+
+    Ok(())
+}
+
+pub unsafe fn destroy_frames(device: &Device, data: &AppData) {
+    data.drawing_data
+        .frames
+        .iter()
+        .for_each(|f| destroy_frame(device, f));
+}
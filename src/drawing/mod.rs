@@ -1,16 +1,26 @@
 use vulkanalia::prelude::v1_0::*;
 
+use self::draw_list::DrawList;
+use self::frame::FrameContext;
+use self::viewport::Viewport;
+
 pub mod command_buffer;
+pub mod draw_list;
+pub mod frame;
 pub mod frame_buffer;
 pub mod render;
+pub mod skybox;
+pub mod viewport;
 
 #[derive(Clone, Debug, Default)]
 pub struct DrawingData {
     pub framebuffers: Vec<vk::Framebuffer>,
     pub command_pool: vk::CommandPool,
-    pub command_buffers: Vec<vk::CommandBuffer>,
-    pub image_available_semaphores: Vec<vk::Semaphore>,
-    pub render_finished_semaphores: Vec<vk::Semaphore>,
-    pub in_flight_fences: Vec<vk::Fence>,
+    pub frames: Vec<FrameContext>,
+    /// Frames the current window has submitted so far.
+    pub frame_count: u64,
+    pub draw_list: DrawList,
+    /// The viewports of the current frame, each drawing the whole draw list.
+    pub viewports: Vec<Viewport>,
     pub images_in_flight: Vec<vk::Fence>,
 }
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use cgmath::SquareMatrix;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::drawing::command_buffer::{self, PushConstants};
+use crate::pipeline::desc::PipelineDesc;
+use crate::texture::{cubemap, manager};
+use crate::AppData;
+
+type Mat4 = cgmath::Matrix4<f32>;
+
+/// Loads the cubemap given with `--skybox` and creates the pipeline that draws it.
+pub unsafe fn create_skybox(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let Some(path) = data.config.skybox.clone() else {
+        return Ok(());
+    };
+
+    data.texture_data.skybox = Some(cubemap::create_cubemap(instance, device, data, &path)?);
+
+    let layout = data.pipeline_data.pipeline_layout;
+    let desc = PipelineDesc::skybox(data);
+    data.pipeline_data.skybox_pipeline = data.pipeline_data.pipelines.get(device, layout, &desc)?;
+
+    info!("Skybox created.");
+    Ok(())
+}
+
+/// Records the skybox into this frame's skybox command buffer, to run after the draw list.
+///
+/// Orthographic viewports have no horizon to show, so they are skipped.
+pub unsafe fn record_skybox(
+    device: &Device,
+    data: &AppData,
+    frame: usize,
+    image_index: usize,
+) -> Result<vk::CommandBuffer> {
+    let context = &data.drawing_data.frames[frame];
+    let command_buffer = context.skybox_command_buffer;
+    let skybox = data.texture_data.skybox.as_ref().unwrap();
+
+    let inheritance = command_buffer::get_inheritance_info(data, image_index);
+
+    let info = vk::CommandBufferBeginInfo::builder()
+        .flags(
+            vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+                | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+        )
+        .inheritance_info(&inheritance);
+
+    device.begin_command_buffer(command_buffer, &info)?;
+
+    device.cmd_bind_pipeline(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        data.pipeline_data.skybox_pipeline,
+    );
+
+    device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        data.pipeline_data.pipeline_layout,
+        0,
+        &[context.descriptor_set, skybox.descriptor_set],
+        &[],
+    );
+
+    for (index, viewport) in data.drawing_data.viewports.iter().enumerate() {
+        if viewport.camera.orthographic.is_some() {
+            continue;
+        }
+
+        command_buffer::set_viewport(device, command_buffer, viewport.rect);
+        command_buffer::push_constants(
+            device,
+            data,
+            command_buffer,
+            &PushConstants {
+                model: Mat4::identity(),
+                material_index: 0,
+                viewport_index: index as u32,
+            },
+        );
+
+        device.cmd_draw(command_buffer, 36, 1, 0, 0);
+    }
+
+    device.end_command_buffer(command_buffer)?;
+
+    Ok(command_buffer)
+}
+
+pub unsafe fn destroy_skybox(device: &Device, data: &mut AppData) -> Result<()> {
+    if let Some(skybox) = data.texture_data.skybox.take() {
+        manager::destroy_texture(device, data, &skybox)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use cgmath::vec3;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::scene::camera::Camera;
+
+type Vec3 = cgmath::Vector3<f32>;
+
+/// The most viewports a layout has, which sizes the uniform buffer.
+pub const MAX_VIEWPORTS: usize = 4;
+
+/// Height of the orthographic views of the quad layout, in world units.
+const ORTHOGRAPHIC_HEIGHT: f32 = 3.0;
+/// Distance of the orthographic cameras from the window camera's target.
+const ORTHOGRAPHIC_DISTANCE: f32 = 5.0;
+
+/// How a window's swapchain is split into viewports.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ViewportLayout {
+    #[default]
+    Single,
+    /// The window's camera next to the view from the opposite side of its target.
+    SideBySide,
+    /// Top, front and side orthographic views, and the window's camera.
+    Quad,
+}
+
+impl ViewportLayout {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "single" => Ok(Self::Single),
+            "side-by-side" => Ok(Self::SideBySide),
+            "quad" => Ok(Self::Quad),
+            _ => Err(anyhow!(
+                "Unknown viewport layout `{}` (expected `single`, `side-by-side` or `quad`).",
+                value
+            )),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Single => Self::SideBySide,
+            Self::SideBySide => Self::Quad,
+            Self::Quad => Self::Single,
+        }
+    }
+
+    /// Splits `extent` into this layout's viewports, which look at the scene around `camera`.
+    pub fn viewports(self, camera: &Camera, extent: vk::Extent2D) -> Vec<Viewport> {
+        match self {
+            Self::Single => vec![Viewport::new(cell(extent, 1, 1, 0), *camera)],
+            Self::SideBySide => {
+                let offset = camera.eye - camera.target;
+                let opposite = Camera {
+                    eye: camera.target + vec3(-offset.x, -offset.y, offset.z),
+                    ..*camera
+                };
+
+                vec![
+                    Viewport::new(cell(extent, 2, 1, 0), *camera),
+                    Viewport::new(cell(extent, 2, 1, 1), opposite),
+                ]
+            }
+            Self::Quad => {
+                let orthographic = |direction: Vec3, up: Vec3| Camera {
+                    eye: camera.target + direction * ORTHOGRAPHIC_DISTANCE,
+                    up,
+                    orthographic: Some(ORTHOGRAPHIC_HEIGHT),
+                    ..*camera
+                };
+
+                let top = orthographic(vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 0.0));
+                let front = orthographic(vec3(0.0, -1.0, 0.0), vec3(0.0, 0.0, 1.0));
+                let side = orthographic(vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+
+                [top, front, side, *camera]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, c)| Viewport::new(cell(extent, 2, 2, i as u32), c))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A sub-rectangle of the swapchain that the scene is drawn into from its own camera.
+#[derive(Copy, Clone, Debug)]
+pub struct Viewport {
+    pub rect: vk::Rect2D,
+    pub camera: Camera,
+}
+
+impl Viewport {
+    pub fn new(rect: vk::Rect2D, camera: Camera) -> Self {
+        Self { rect, camera }
+    }
+}
+
+/// The `index`-th cell, in row-major order, of a grid over `extent`.
+fn cell(extent: vk::Extent2D, columns: u32, rows: u32, index: u32) -> vk::Rect2D {
+    let (column, row) = (index % columns, index / columns);
+
+    let x = extent.width * column / columns;
+    let y = extent.height * row / rows;
+    let width = extent.width * (column + 1) / columns - x;
+    let height = extent.height * (row + 1) / rows - y;
+
+    // Viewports can't be empty, even in a window only a few pixels wide.
+    vk::Rect2D::builder()
+        .offset(vk::Offset2D {
+            x: x as i32,
+            y: y as i32,
+        })
+        .extent(vk::Extent2D {
+            width: width.max(1),
+            height: height.max(1),
+        })
+        .build()
+}
@@ -1,14 +1,18 @@
 #![allow(
     dead_code,
     unused_variables,
+    clippy::module_inception,
     clippy::too_many_arguments,
     clippy::unnecessary_wraps
 )]
 
+use std::mem;
 use std::time::Instant;
 
 use anyhow::{anyhow, Result};
 use log::*;
+use presentation::window::{WindowData, WindowRole};
+use profiling::trace;
 use uniform::descriptor;
 use vulkanalia::loader::{LibloadingLoader, LIBRARY};
 use vulkanalia::prelude::v1_0::*;
@@ -17,14 +21,20 @@ use vulkanalia::vk::{
 };
 use vulkanalia::{window, Entry, Instance};
 use winit::dpi::LogicalSize;
-use winit::event::{Event, WindowEvent};
+use winit::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent,
+};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::{Window, WindowBuilder};
+use winit::window::{Window, WindowBuilder, WindowId};
 
+mod config;
+mod culling;
 mod drawing;
 mod model;
 mod pipeline;
 mod presentation;
+mod profiling;
+mod scene;
 mod setup;
 mod texture;
 mod uniform;
@@ -38,60 +48,135 @@ const VALIDATION_LAYER: vk::ExtensionName =
 fn main() -> Result<()> {
     pretty_env_logger::init();
 
+    let config = config::Config::from_args(std::env::args().skip(1))?;
+
     // Window
 
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
+    let mut windows = vec![WindowBuilder::new()
         .with_title("Vulkan Tutorial (Rust)")
         .with_inner_size(LogicalSize::new(1024, 768))
-        .build(&event_loop)?;
+        .build(&event_loop)?];
+
+    if config.material_preview {
+        windows.push(
+            WindowBuilder::new()
+                .with_title("Material Preview")
+                .with_inner_size(LogicalSize::new(512, 512))
+                .build(&event_loop)?,
+        );
+    }
 
     // App
 
-    let mut app = unsafe { App::create(&window)? };
+    let mut app = unsafe { App::create(&windows, config)? };
     let mut destroying = false;
-    let mut minimized = false;
+    let mut modifiers = ModifiersState::empty();
+
+    let fullscreen = app.data.config.fullscreen;
+    app.with_window(0, |app| {
+        presentation::fullscreen::set_fullscreen(&windows[0], &mut app.data, fullscreen)
+    });
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
         match event {
-            // Render a frame if our Vulkan app is not being destroyed.
-            Event::MainEventsCleared if !destroying && !minimized => {
-                unsafe { app.render(&window) }.unwrap()
+            // Exit once a benchmark has rendered all of its frames.
+            Event::MainEventsCleared if !destroying && app.bench_finished() => {
+                destroying = true;
+                *control_flow = ControlFlow::Exit;
+                unsafe {
+                    app.device.device_wait_idle().unwrap();
+                }
+                unsafe {
+                    app.destroy();
+                }
             }
+            // Render a frame if our Vulkan app is not being destroyed.
+            Event::MainEventsCleared if !destroying => unsafe { app.render(&windows) }.unwrap(),
             // Trigger re-render, if resized.
             Event::WindowEvent {
+                window_id,
                 event: WindowEvent::Resized(size),
-                ..
             } => {
+                let Some(index) = window_index(&windows, window_id) else {
+                    return;
+                };
+
+                let window = &mut app.data.windows[index];
                 if size.width == 0 || size.height == 0 {
-                    minimized = true;
+                    window.minimized = true;
                 } else {
-                    minimized = false;
-                    app.resized = true;
+                    window.minimized = false;
+                    window.resized = true;
                 }
 
                 info!("Resized window.");
             }
-            // Destroy our Vulkan app.
+            // Track modifiers for Alt+Enter.
             Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
+                event: WindowEvent::ModifiersChanged(state),
                 ..
+            } => modifiers = state,
+            // Toggle fullscreen on F11 or Alt+Enter, switch view mode on number keys.
+            Event::WindowEvent {
+                window_id,
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(key),
+                                ..
+                            },
+                        ..
+                    },
             } => {
-                destroying = true;
-                *control_flow = ControlFlow::Exit;
-                unsafe {
-                    app.device.device_wait_idle().unwrap();
-                }
-                unsafe {
-                    app.destroy();
+                let Some(index) = window_index(&windows, window_id) else {
+                    return;
+                };
+
+                if key == VirtualKeyCode::F11 || (key == VirtualKeyCode::Return && modifiers.alt())
+                {
+                    app.with_window(index, |app| {
+                        presentation::fullscreen::toggle_fullscreen(&windows[index], &mut app.data)
+                    });
+                } else if let Some(mode) = pipeline::view_mode::ViewMode::from_key(key) {
+                    unsafe { app.set_view_mode(mode) }.unwrap();
+                } else {
+                    app.with_window(index, |app| app.handle_key(key));
                 }
             }
+            // Close a window other than the first, or destroy our Vulkan app with the first.
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::CloseRequested,
+            } => match window_index(&windows, window_id) {
+                Some(index) if index > 0 => {
+                    unsafe { app.close_window(index) }.unwrap();
+                    windows.remove(index);
+                }
+                _ => {
+                    destroying = true;
+                    *control_flow = ControlFlow::Exit;
+                    unsafe {
+                        app.device.device_wait_idle().unwrap();
+                    }
+                    unsafe {
+                        app.destroy();
+                    }
+                }
+            },
             _ => {}
         }
     });
 }
 
+/// The index of the window an event was sent to, in the order the windows were created.
+fn window_index(windows: &[Window], id: WindowId) -> Option<usize> {
+    windows.iter().position(|w| w.id() == id)
+}
+
 /// Our Vulkan app.
 #[derive(Clone, Debug)]
 struct App {
@@ -99,6 +184,7 @@ struct App {
     instance: Instance,
     data: AppData,
     device: Device,
+    /// The frame and resize flag of the window whose state is swapped into `data`.
     frame: usize,
     resized: bool,
     start: Instant,
@@ -106,14 +192,53 @@ struct App {
 
 impl App {
     /// Creates our Vulkan app.
-    unsafe fn create(window: &Window) -> Result<Self> {
+    unsafe fn create(windows: &[Window], config: config::Config) -> Result<Self> {
         let loader = LibloadingLoader::new(LIBRARY)?;
         let entry = Entry::new(loader).map_err(|b| anyhow!("{}", b))?;
 
-        let mut data = AppData::default();
+        let mut data = AppData {
+            config,
+            ..Default::default()
+        };
+        let frames_in_flight = data.config.frames_in_flight;
+        data.windows = (0..windows.len())
+            .map(|i| {
+                let role = match i {
+                    0 => WindowRole::Scene,
+                    _ => WindowRole::MaterialPreview,
+                };
+                let mut window = WindowData::new(role, i * frames_in_flight);
+                window.presentation_data.present_mode_setting = data.config.present_mode;
+                if role == WindowRole::Scene {
+                    window.viewport_layout = data.config.viewport_layout;
+                }
+                window
+            })
+            .collect();
+
+        // The shared resources are created with the first window's state swapped in.
+        presentation::window::swap_window(&mut data, 0);
+
+        data.profiling_data.trace = data
+            .config
+            .trace_path
+            .clone()
+            .map(|p| trace::Trace::new(p, frames_in_flight * windows.len()));
+        data.profiling_data.bench = data.config.bench_frames.map(|frames| {
+            profiling::bench::Bench::new(
+                frames,
+                data.config.bench_warmup_frames,
+                data.config.bench_json_path.clone(),
+                frames_in_flight * windows.len(),
+            )
+        });
+        let window = &windows[0];
         let instance = setup::instance::create_instance(window, &entry, &mut data)?;
 
         data.surface = window::create_surface(&instance, &window, &window)?;
+        for (index, window) in windows.iter().enumerate().skip(1) {
+            data.windows[index].surface = window::create_surface(&instance, &window, &window)?;
+        }
 
         setup::device::pick_physical_device(&instance, &mut data)?;
         let device = setup::device::create_logical_device(&entry, &instance, &mut data)?;
@@ -124,25 +249,34 @@ impl App {
         pipeline::pipeline::create_render_pass(&instance, &device, &mut data)?;
 
         uniform::descriptor::create_descriptor_set_layout(&device, &mut data)?;
+        texture::manager::create_texture_descriptor_objects(&device, &mut data)?;
 
         pipeline::pipeline::create_pipeline(&device, &mut data)?;
+        culling::frustum::create_culling_pipeline(&device, &mut data)?;
 
         drawing::command_buffer::create_command_pool(&instance, &device, &mut data)?;
+        profiling::timestamp::create_timestamp_query_pools(&instance, &device, &mut data)?;
+        profiling::statistics::create_statistics_query_pools(&device, &mut data)?;
+        culling::occlusion::create_occlusion_query_pools(&device, &mut data)?;
 
         texture::image::create_color_objects(&instance, &device, &mut data)?;
         texture::image::create_depth_objects(&instance, &device, &mut data)?;
 
         drawing::frame_buffer::create_framebuffers(&device, &mut data)?;
 
-        texture::image::create_texture_image(&instance, &device, &mut data)?;
-        texture::image::create_texture_image_view(&device, &mut data)?;
-        texture::image::create_texture_sampler(&device, &mut data)?;
+        texture::sampler::create_texture_sampler(&instance, &device, &mut data)?;
+        texture::material::create_materials(&instance, &device, &mut data)?;
+        drawing::skybox::create_skybox(&instance, &device, &mut data)?;
 
         model::model::load_model(&mut data)?;
+        scene::object::create_scene(&mut data);
 
         vertex::vertex::create_vertex_buffer(&instance, &device, &mut data)?;
         vertex::vertex::create_index_buffer(&instance, &device, &mut data)?;
 
+        vertex::instance::create_instance_buffers(&instance, &device, &mut data)?;
+        culling::frustum::create_culling_buffers(&instance, &device, &mut data)?;
+
         uniform::descriptor::create_uniform_buffers(&instance, &device, &mut data)?;
         uniform::descriptor::create_descriptor_pool(&device, &mut data)?;
         uniform::descriptor::create_descriptor_sets(&device, &mut data)?;
@@ -150,6 +284,13 @@ impl App {
         drawing::command_buffer::create_command_buffers(&device, &mut data)?;
         drawing::render::create_sync_objects(&device, &mut data)?;
 
+        presentation::window::swap_window(&mut data, 0);
+        for (index, window) in windows.iter().enumerate().skip(1) {
+            presentation::window::swap_window(&mut data, index);
+            presentation::window::create_window_swapchain(window, &instance, &device, &mut data)?;
+            presentation::window::swap_window(&mut data, index);
+        }
+
         Ok(Self {
             entry,
             instance,
@@ -161,43 +302,137 @@ impl App {
         })
     }
 
-    /// Renders a frame for our Vulkan app.
-    unsafe fn render(&mut self, window: &Window) -> Result<()> {
-        self.device.wait_for_fences(
-            &[self.data.drawing_data.in_flight_fences[self.frame]],
-            true,
-            u64::MAX,
-        )?;
+    /// Renders a frame in each window of our Vulkan app that isn't minimized.
+    unsafe fn render(&mut self, windows: &[Window]) -> Result<()> {
+        // State shared by the windows advances once per frame, not once per window.
+        profiling::timestamp::update_frame_time(&mut self.data);
+        profiling::bench::record_cpu_frame(&mut self.data);
+        culling::occlusion::advance_occluded_objects(&mut self.data);
 
-        let image_index = self
-            .device
-            .acquire_next_image_khr(
-                self.data.presentation_data.swapchain,
-                u64::MAX,
-                self.data.drawing_data.image_available_semaphores[self.frame],
-                vk::Fence::null(),
-            )?
-            .0 as usize;
+        for (index, window) in windows.iter().enumerate() {
+            if !self.data.windows[index].minimized {
+                self.with_window(index, |app| app.render_window(window, index))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders a frame for the window whose state is swapped into `data`.
+    unsafe fn render_window(&mut self, window: &Window, index: usize) -> Result<()> {
+        let frame_start = Instant::now();
+        let in_flight_fence = self.data.drawing_data.frames[self.frame].in_flight_fence;
 
-        if !self.data.drawing_data.images_in_flight[image_index as usize].is_null() {
+        self.device
+            .wait_for_fences(&[in_flight_fence], true, u64::MAX)?;
+        trace::span(&mut self.data, "fence wait", frame_start);
+
+        // Every frame up to the one last submitted with this frame's resources has completed.
+        let completed_frames = (self.data.drawing_data.frame_count + 1)
+            .saturating_sub(self.data.config.frames_in_flight as u64);
+        presentation::swapchain::destroy_retired_swapchains(
+            &self.device,
+            &mut self.data,
+            completed_frames,
+        );
+
+        let start = Instant::now();
+        profiling::timestamp::update_timings(&self.device, &mut self.data, self.frame)?;
+        profiling::bench::record_gpu_frame(&mut self.data, self.frame);
+        profiling::statistics::update_statistics(&self.device, &mut self.data, self.frame)?;
+        culling::occlusion::update_occluded_objects(&self.device, &mut self.data, self.frame)?;
+        trace::span(&mut self.data, "query readback", start);
+
+        let start = Instant::now();
+        let result = self.device.acquire_next_image_khr(
+            self.data.presentation_data.swapchain,
+            u64::MAX,
+            self.data.drawing_data.frames[self.frame].image_available_semaphore,
+            vk::Fence::null(),
+        );
+
+        let image_index = match result {
+            Ok((image_index, _)) => image_index as usize,
+            Err(vk::ErrorCode::OUT_OF_DATE_KHR) => {
+                return presentation::swapchain::recreate_swapchain(self, window)
+            }
+            Err(e) => return Err(anyhow!(e)),
+        };
+
+        if !self.data.drawing_data.images_in_flight[image_index].is_null() {
             self.device.wait_for_fences(
-                &[self.data.drawing_data.images_in_flight[image_index as usize]],
+                &[self.data.drawing_data.images_in_flight[image_index]],
                 true,
                 u64::MAX,
             )?;
         }
 
-        self.data.drawing_data.images_in_flight[image_index as usize] =
-            self.data.drawing_data.in_flight_fences[self.frame];
+        self.data.drawing_data.images_in_flight[image_index] = in_flight_fence;
+        trace::span(&mut self.data, "acquire", start);
+
+        let start = Instant::now();
+        let time = match &self.data.profiling_data.bench {
+            Some(bench) => bench.time(),
+            None => self.start.elapsed().as_secs_f32(),
+        };
+        let scene = self.data.windows[index]
+            .scene
+            .as_ref()
+            .unwrap_or(&self.data.scene_data);
+        self.data.drawing_data.draw_list = drawing::draw_list::DrawList::build(scene, time);
+        self.data.drawing_data.viewports = self.data.viewport_layout.viewports(
+            &self.data.camera,
+            self.data.presentation_data.swapchain_extent,
+        );
+        if self.data.windows[index].scene.is_some() {
+            // Occlusion results are applied to the objects of the shared scene.
+            self.data.culling_data.occlusion_objects[self.frame].clear();
+        } else {
+            culling::occlusion::prepare_occlusion_queries(
+                &self.device,
+                &mut self.data,
+                self.frame,
+            )?;
+        }
+        trace::span(&mut self.data, "draw list", start);
+
+        let start = Instant::now();
+        descriptor::update_uniform_buffer(self, self.frame)?;
+        if culling::frustum::culling_active(&self.data) {
+            culling::frustum::update_culling_buffers(
+                &self.instance,
+                &self.device,
+                &mut self.data,
+                self.frame,
+            )?;
+        } else {
+            vertex::instance::update_instance_buffer(
+                &self.instance,
+                &self.device,
+                &mut self.data,
+                self.frame,
+            )?;
+        }
+        trace::span(&mut self.data, "buffer updates", start);
+
+        let start = Instant::now();
+        drawing::command_buffer::record_command_buffer(
+            &self.device,
+            &self.data,
+            self.frame,
+            image_index,
+        )?;
+        trace::span(&mut self.data, "record", start);
 
-        descriptor::update_uniform_buffer(self, image_index)?;
+        let start = Instant::now();
 
-        let wait_semaphores = &[self.data.drawing_data.image_available_semaphores[self.frame]];
+        let context = &self.data.drawing_data.frames[self.frame];
+        let wait_semaphores = &[context.image_available_semaphore];
 
         let wait_stages = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let command_buffers = &[self.data.drawing_data.command_buffers[image_index as usize]];
+        let command_buffers = &[context.command_buffer];
 
-        let signal_semaphores = &[self.data.drawing_data.render_finished_semaphores[self.frame]];
+        let signal_semaphores = &[context.render_finished_semaphore];
 
         let submit_info = vk::SubmitInfo::builder()
             .wait_semaphores(wait_semaphores)
@@ -205,15 +440,22 @@ impl App {
             .command_buffers(command_buffers)
             .signal_semaphores(signal_semaphores);
 
-        self.device
-            .reset_fences(&[self.data.drawing_data.in_flight_fences[self.frame]])?;
+        self.device.reset_fences(&[in_flight_fence])?;
 
         self.device.queue_submit(
             self.data.setup_data.graphics_queue,
             &[submit_info],
-            self.data.drawing_data.in_flight_fences[self.frame],
+            in_flight_fence,
         )?;
 
+        self.data.drawing_data.frame_count += 1;
+        self.data.profiling_data.queries_pending[self.frame] = true;
+        trace::mark_submit(&mut self.data, self.frame);
+        profiling::bench::mark_submit(&mut self.data, self.frame);
+        trace::span(&mut self.data, "submit", start);
+
+        let start = Instant::now();
+
         let swapchains = &[self.data.presentation_data.swapchain];
         let image_indices = &[image_index as u32];
         let present_info = vk::PresentInfoKHR::builder()
@@ -227,37 +469,197 @@ impl App {
 
         let changed = result == Ok(vk::SuccessCode::SUBOPTIMAL_KHR)
             || result == Err(vk::ErrorCode::OUT_OF_DATE_KHR);
+        trace::span(&mut self.data, "present", start);
 
         if self.resized || changed {
             self.resized = false;
+            let start = Instant::now();
             presentation::swapchain::recreate_swapchain(self, window)?;
+            trace::span(&mut self.data, "recreate swapchain", start);
         } else if let Err(e) = result {
             return Err(anyhow!(e));
         }
 
-        self.frame = (self.frame + 1) % drawing::render::MAX_FRAMES_IN_FLIGHT;
+        trace::span(&mut self.data, "frame", frame_start);
+        let first_frame = self.data.windows[index].first_frame;
+        self.frame =
+            first_frame + (self.frame - first_frame + 1) % self.data.config.frames_in_flight;
+
+        Ok(())
+    }
+
+    /// Runs `f` with the state of the window at `index` swapped into `data`.
+    fn with_window<T>(&mut self, index: usize, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.swap_window(index);
+        let result = f(self);
+        self.swap_window(index);
+        result
+    }
+
+    fn swap_window(&mut self, index: usize) {
+        presentation::window::swap_window(&mut self.data, index);
+
+        let window = &mut self.data.windows[index];
+        mem::swap(&mut window.frame, &mut self.frame);
+        mem::swap(&mut window.resized, &mut self.resized);
+    }
+
+    /// Destroys a window other than the first, along with the resources of its frames in flight.
+    unsafe fn close_window(&mut self, index: usize) -> Result<()> {
+        self.device.device_wait_idle()?;
+
+        self.destroy_window(index);
+        let window = self.data.windows.remove(index);
+
+        let frames = window.first_frame..window.first_frame + self.data.config.frames_in_flight;
+        drawing::frame::release_frames(&self.device, &mut self.data, frames)?;
+
+        info!("Closed window {}.", index);
+        Ok(())
+    }
+
+    /// Destroys the swapchain resources and surface of a window.
+    unsafe fn destroy_window(&mut self, index: usize) {
+        self.with_window(index, |app| {
+            presentation::swapchain::destroy_swapchain(app);
+            app.instance.destroy_surface_khr(app.data.surface, None);
+        });
+    }
+
+    /// Whether a `--bench` run has rendered all of its frames.
+    fn bench_finished(&self) -> bool {
+        self.data
+            .profiling_data
+            .bench
+            .as_ref()
+            .is_some_and(|b| b.finished())
+    }
+
+    /// Switches the pipeline used to draw the scene.
+    unsafe fn set_view_mode(&mut self, mode: pipeline::view_mode::ViewMode) -> Result<()> {
+        if mode == self.data.pipeline_data.view_mode {
+            return Ok(());
+        }
+
+        if !mode.is_supported(&self.data) {
+            warn!("View mode {:?} is not supported by this device.", mode);
+            return Ok(());
+        }
+
+        let desc = mode.pipeline_desc(&self.data);
+        self.data.pipeline_data.pipeline = self.data.pipeline_data.pipelines.get(
+            &self.device,
+            self.data.pipeline_data.pipeline_layout,
+            &desc,
+        )?;
+        self.data.pipeline_data.view_mode = mode;
 
+        info!("View mode set to {:?}.", mode);
         Ok(())
     }
 
+    /// Handles key presses that change what is drawn.
+    fn handle_key(&mut self, key: VirtualKeyCode) {
+        let scene = &mut self.data.scene_data;
+        match key {
+            VirtualKeyCode::C => {
+                self.data.culling_data.enabled = !self.data.culling_data.enabled;
+                info!("GPU culling enabled: {}.", self.data.culling_data.enabled);
+            }
+            VirtualKeyCode::V => {
+                let presentation = &mut self.data.presentation_data;
+                presentation.present_mode_setting = presentation.present_mode_setting.next();
+                info!(
+                    "Present mode set to {:?}.",
+                    presentation.present_mode_setting
+                );
+                // The swapchain is recreated with the new mode after the next frame.
+                self.resized = true;
+            }
+            VirtualKeyCode::L => {
+                self.data.viewport_layout = self.data.viewport_layout.next();
+                info!("Viewport layout set to {:?}.", self.data.viewport_layout);
+            }
+            VirtualKeyCode::O => {
+                let culling = &mut self.data.culling_data;
+                culling.occlusion_enabled = !culling.occlusion_enabled;
+                info!("Occlusion culling enabled: {}.", culling.occlusion_enabled);
+            }
+            VirtualKeyCode::Equals => {
+                let id = scene.add(scene::object::SceneObject::new(
+                    scene::object::grid_transform(scene.objects.len()),
+                ));
+                info!("Added object {:?}.", id);
+            }
+            VirtualKeyCode::Minus => {
+                if let Some(object) = scene.objects.last().map(|o| o.id) {
+                    scene.remove(object);
+                    info!("Removed object {:?}.", object);
+                }
+            }
+            VirtualKeyCode::H => {
+                if let Some(object) = scene.objects.first().map(|o| (o.id, o.visible)) {
+                    scene.set_visible(object.0, !object.1);
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Destroys our Vulkan app.
     unsafe fn destroy(&mut self) {
-        presentation::swapchain::destroy_swapchain(self);
-
-        self.device
-            .destroy_sampler(self.data.texture_data.texture_sampler, None);
+        if let Err(e) = profiling::bench::finish(&self.device, &mut self.data) {
+            error!("Failed to read the last benchmark frames: {}", e);
+        }
+        if let Err(e) = trace::write_trace(&self.data) {
+            error!("Failed to write trace: {}", e);
+        }
+        self.with_window(0, |app| {
+            if let Err(e) = profiling::bench::report(&app.data) {
+                error!("Failed to write benchmark report: {}", e);
+            }
+        });
 
-        self.device
-            .destroy_image_view(self.data.texture_data.texture_image_view, None);
+        for index in 0..self.data.windows.len() {
+            self.destroy_window(index);
+        }
 
+        self.data.pipeline_data.pipelines.destroy(&self.device);
         self.device
-            .destroy_image(self.data.texture_data.texture_image, None);
+            .destroy_pipeline_layout(self.data.pipeline_data.pipeline_layout, None);
         self.device
-            .free_memory(self.data.texture_data.texture_image_memory, None);
+            .destroy_render_pass(self.data.pipeline_data.render_pass, None);
+
+        self.data.texture_data.samplers.destroy(&self.device);
+
+        if let Err(e) = drawing::skybox::destroy_skybox(&self.device, &mut self.data) {
+            error!("Failed to destroy skybox: {}", e);
+        }
+        if let Err(e) = texture::material::destroy_materials(&self.device, &mut self.data) {
+            error!("Failed to release materials: {}", e);
+        }
+        texture::manager::destroy_textures(&self.device, &mut self.data);
 
         self.device
             .destroy_descriptor_set_layout(self.data.uniform_data.descriptor_set_layout, None);
 
+        self.device
+            .destroy_descriptor_pool(self.data.uniform_data.descriptor_pool, None);
+
+        self.data
+            .vertex_data
+            .instance_buffers
+            .iter()
+            .for_each(|b| self.device.destroy_buffer(*b, None));
+        self.data
+            .vertex_data
+            .instance_buffers_memory
+            .iter()
+            .for_each(|m| self.device.free_memory(*m, None));
+
+        culling::frustum::destroy_culling_buffers(&self.device, &self.data);
+        culling::frustum::destroy_culling_pipeline(&self.device, &self.data);
+
         self.device
             .destroy_buffer(self.data.vertex_data.index_buffer, None);
         self.device
@@ -268,23 +670,10 @@ impl App {
         self.device
             .free_memory(self.data.vertex_data.vertex_buffer_memory, None);
 
-        self.data
-            .drawing_data
-            .in_flight_fences
-            .iter()
-            .for_each(|f| self.device.destroy_fence(*f, None));
-
-        self.data
-            .drawing_data
-            .render_finished_semaphores
-            .iter()
-            .for_each(|s| self.device.destroy_semaphore(*s, None));
-        self.data
-            .drawing_data
-            .image_available_semaphores
-            .iter()
-            .for_each(|s| self.device.destroy_semaphore(*s, None));
-
+        culling::occlusion::destroy_occlusion_query_pools(&self.device, &self.data);
+        profiling::statistics::destroy_statistics_query_pools(&self.device, &self.data);
+        profiling::timestamp::destroy_timestamp_query_pools(&self.device, &self.data);
+        drawing::frame::destroy_frames(&self.device, &self.data);
         self.device
             .destroy_command_pool(self.data.drawing_data.command_pool, None);
 
@@ -295,7 +684,6 @@ impl App {
                 .destroy_debug_utils_messenger_ext(self.data.setup_data.messenger, None);
         }
 
-        self.instance.destroy_surface_khr(self.data.surface, None);
         self.instance.destroy_instance(None);
     }
 }
@@ -303,12 +691,20 @@ impl App {
 /// The Vulkan handles and associated properties used by our Vulkan app.
 #[derive(Clone, Debug, Default)]
 struct AppData {
+    config: config::Config,
+    /// Every window; the state of the one being worked on is swapped into the fields below.
+    windows: Vec<presentation::window::WindowData>,
     surface: vk::SurfaceKHR,
+    camera: scene::camera::Camera,
+    viewport_layout: drawing::viewport::ViewportLayout,
     setup_data: setup::SetupData,
     presentation_data: presentation::PresentationData,
     uniform_data: uniform::UniformData,
     pipeline_data: pipeline::PipelineData,
     drawing_data: drawing::DrawingData,
+    culling_data: culling::CullingData,
+    profiling_data: profiling::ProfilingData,
+    scene_data: scene::SceneData,
     vertex_data: vertex::VertexData,
     texture_data: texture::TextureData,
 }
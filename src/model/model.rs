@@ -4,14 +4,17 @@ use std::hash::{Hash, Hasher};
 use std::io::BufReader;
 
 use anyhow::Result;
-use cgmath::{vec2, vec3};
+use cgmath::{vec2, vec3, InnerSpace};
 
 use crate::vertex::vertex;
 use crate::AppData;
 
 impl PartialEq for vertex::Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.pos == other.pos && self.color == other.color && self.tex_coord == other.tex_coord
+        self.pos == other.pos
+            && self.color == other.color
+            && self.tex_coord == other.tex_coord
+            && self.normal == other.normal
     }
 }
 
@@ -27,6 +30,9 @@ impl Hash for vertex::Vertex {
         self.color[2].to_bits().hash(state);
         self.tex_coord[0].to_bits().hash(state);
         self.tex_coord[1].to_bits().hash(state);
+        self.normal[0].to_bits().hash(state);
+        self.normal[1].to_bits().hash(state);
+        self.normal[2].to_bits().hash(state);
     }
 }
 
@@ -47,10 +53,23 @@ pub fn load_model(data: &mut AppData) -> Result<()> {
     let mut unique_vertices = HashMap::new();
 
     for model in &models {
-        for index in &model.mesh.indices {
+        for (i, index) in model.mesh.indices.iter().enumerate() {
             let pos_offset = (3 * index) as usize;
             let tex_coord_offset = (2 * index) as usize;
 
+            // Meshes without normals get a zero normal; the debug view falls back to face normals.
+            let normal = match model.mesh.normal_indices.get(i) {
+                Some(n) => {
+                    let normal_offset = (3 * n) as usize;
+                    vec3(
+                        model.mesh.normals[normal_offset],
+                        model.mesh.normals[normal_offset + 1],
+                        model.mesh.normals[normal_offset + 2],
+                    )
+                }
+                None => vec3(0.0, 0.0, 0.0),
+            };
+
             let vertex = vertex::Vertex {
                 pos: vec3(
                     model.mesh.positions[pos_offset],
@@ -62,6 +81,7 @@ pub fn load_model(data: &mut AppData) -> Result<()> {
                     model.mesh.texcoords[tex_coord_offset],
                     1.0 - model.mesh.texcoords[tex_coord_offset + 1],
                 ),
+                normal,
             };
 
             if let Some(index) = unique_vertices.get(&vertex) {
@@ -75,5 +95,25 @@ pub fn load_model(data: &mut AppData) -> Result<()> {
         }
     }
 
+    // A bounding sphere around the mesh, used for frustum culling.
+    let (min, max) = data.vertex_data.vertices.iter().fold(
+        (vec3(f32::MAX, f32::MAX, f32::MAX), vec3(f32::MIN, f32::MIN, f32::MIN)),
+        |(min, max), v| {
+            (
+                vec3(min.x.min(v.pos.x), min.y.min(v.pos.y), min.z.min(v.pos.z)),
+                vec3(max.x.max(v.pos.x), max.y.max(v.pos.y), max.z.max(v.pos.z)),
+            )
+        },
+    );
+
+    let center = (min + max) / 2.0;
+    data.vertex_data.bounds_center = center.into();
+    data.vertex_data.bounds_radius = data
+        .vertex_data
+        .vertices
+        .iter()
+        .map(|v| (v.pos - center).magnitude())
+        .fold(0.0, f32::max);
+
     Ok(())
 }
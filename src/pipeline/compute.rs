@@ -0,0 +1,29 @@
+use anyhow::Result;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::pipeline::pipeline;
+
+pub unsafe fn create_compute_pipeline(
+    device: &Device,
+    layout: vk::PipelineLayout,
+    bytecode: &[u8],
+) -> Result<vk::Pipeline> {
+    let shader_module = pipeline::create_shader_module(device, bytecode)?;
+
+    let stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(shader_module)
+        .name(b"main\0");
+
+    let info = vk::ComputePipelineCreateInfo::builder()
+        .stage(stage)
+        .layout(layout);
+
+    let pipeline = device
+        .create_compute_pipelines(vk::PipelineCache::null(), &[info], None)?
+        .0[0];
+
+    device.destroy_shader_module(shader_module, None);
+
+    Ok(pipeline)
+}
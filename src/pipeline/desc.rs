@@ -0,0 +1,246 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+
+use vulkanalia::prelude::v1_0::*;
+
+use crate::presentation::surface_format::OutputEncoding;
+use crate::vertex::{instance, vertex};
+use crate::AppData;
+
+pub const SHADER_VERT: &[u8] = include_bytes!("../vertex/shaders/vert.spv");
+pub const SHADER_FRAG: &[u8] = include_bytes!("../vertex/shaders/frag.spv");
+pub const SHADER_SKYBOX_VERT: &[u8] = include_bytes!("../vertex/shaders/skybox_vert.spv");
+pub const SHADER_SKYBOX_FRAG: &[u8] = include_bytes!("../vertex/shaders/skybox_frag.spv");
+
+/// A hash of a `PipelineDesc` and the layout it's built with, used to deduplicate pipelines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey(u64);
+
+/// Shaders are `include_bytes!` constants, so they're compared and hashed by address rather than
+/// by their SPIR-V.
+#[derive(Copy, Clone, Debug)]
+pub struct ShaderDesc {
+    pub vertex: &'static [u8],
+    pub fragment: &'static [u8],
+    /// Value of fragment specialization constant 0, ignored by shaders that don't declare it.
+    pub specialization: u32,
+    /// Fragment specialization constant 1.
+    pub output_encoding: OutputEncoding,
+}
+
+impl PartialEq for ShaderDesc {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.vertex, other.vertex)
+            && ptr::eq(self.fragment, other.fragment)
+            && self.specialization == other.specialization
+            && self.output_encoding == other.output_encoding
+    }
+}
+
+impl Eq for ShaderDesc {}
+
+impl Hash for ShaderDesc {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.vertex.as_ptr().hash(state);
+        self.fragment.as_ptr().hash(state);
+        self.specialization.hash(state);
+        self.output_encoding.hash(state);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayoutDesc {
+    pub bindings: Vec<vk::VertexInputBindingDescription>,
+    pub attributes: Vec<vk::VertexInputAttributeDescription>,
+}
+
+impl VertexLayoutDesc {
+    pub fn vertex() -> Self {
+        Self {
+            bindings: vec![
+                vertex::Vertex::binding_description(),
+                instance::InstanceData::binding_description(),
+            ],
+            attributes: vertex::Vertex::attribute_descriptions()
+                .iter()
+                .chain(instance::InstanceData::attribute_descriptions().iter())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// No vertex input, for shaders that generate their vertices.
+    pub fn none() -> Self {
+        Self {
+            bindings: vec![],
+            attributes: vec![],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RasterDesc {
+    pub topology: vk::PrimitiveTopology,
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+}
+
+impl Default for RasterDesc {
+    fn default() -> Self {
+        Self {
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DepthStencilDesc {
+    pub test: bool,
+    pub write: bool,
+    pub compare_op: vk::CompareOp,
+}
+
+impl Default for DepthStencilDesc {
+    fn default() -> Self {
+        Self {
+            test: true,
+            write: true,
+            compare_op: vk::CompareOp::LESS,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    Alpha,
+    Additive,
+}
+
+/// The parts of a render pass a pipeline has to be compatible with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderPassDesc {
+    pub render_pass: vk::RenderPass,
+    pub subpass: u32,
+    pub samples: vk::SampleCountFlags,
+    pub sample_shading: bool,
+}
+
+impl RenderPassDesc {
+    pub fn main(data: &AppData) -> Self {
+        Self {
+            render_pass: data.pipeline_data.render_pass,
+            subpass: 0,
+            samples: data.texture_data.msaa_samples,
+            sample_shading: true,
+        }
+    }
+}
+
+/// Everything needed to build a graphics pipeline.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineDesc {
+    pub shaders: ShaderDesc,
+    pub vertex_layout: VertexLayoutDesc,
+    pub raster: RasterDesc,
+    pub depth_stencil: DepthStencilDesc,
+    pub blend: BlendMode,
+    pub render_pass: RenderPassDesc,
+}
+
+impl PipelineDesc {
+    pub fn key(&self, layout: vk::PipelineLayout) -> PipelineKey {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        layout.hash(&mut hasher);
+        PipelineKey(hasher.finish())
+    }
+
+    pub fn opaque(data: &AppData) -> Self {
+        Self {
+            shaders: ShaderDesc {
+                vertex: SHADER_VERT,
+                fragment: SHADER_FRAG,
+                specialization: 0,
+                output_encoding: data.presentation_data.output_encoding,
+            },
+            vertex_layout: VertexLayoutDesc::vertex(),
+            raster: RasterDesc::default(),
+            depth_stencil: DepthStencilDesc::default(),
+            blend: BlendMode::Opaque,
+            render_pass: RenderPassDesc::main(data),
+        }
+    }
+
+    pub fn transparent(data: &AppData) -> Self {
+        Self {
+            depth_stencil: DepthStencilDesc {
+                write: false,
+                ..Default::default()
+            },
+            blend: BlendMode::Alpha,
+            ..Self::opaque(data)
+        }
+    }
+
+    /// The skybox cube, drawn on the far plane behind everything already drawn.
+    pub fn skybox(data: &AppData) -> Self {
+        Self {
+            shaders: ShaderDesc {
+                vertex: SHADER_SKYBOX_VERT,
+                fragment: SHADER_SKYBOX_FRAG,
+                specialization: 0,
+                output_encoding: data.presentation_data.output_encoding,
+            },
+            vertex_layout: VertexLayoutDesc::none(),
+            raster: RasterDesc {
+                cull_mode: vk::CullModeFlags::NONE,
+                ..Default::default()
+            },
+            depth_stencil: DepthStencilDesc {
+                write: false,
+                compare_op: vk::CompareOp::LESS_OR_EQUAL,
+                ..Default::default()
+            },
+            ..Self::opaque(data)
+        }
+    }
+
+    pub fn wireframe(data: &AppData) -> Self {
+        Self {
+            raster: RasterDesc {
+                polygon_mode: vk::PolygonMode::LINE,
+                cull_mode: vk::CullModeFlags::NONE,
+                ..Default::default()
+            },
+            ..Self::opaque(data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_includes_layout() {
+        let desc = PipelineDesc::opaque(&AppData::default());
+        let (a, b) = (
+            vk::PipelineLayout::from_raw(1),
+            vk::PipelineLayout::from_raw(2),
+        );
+
+        assert_eq!(desc.key(a), desc.clone().key(a));
+        assert_ne!(desc.key(a), desc.key(b));
+        assert_ne!(
+            desc.key(a),
+            PipelineDesc::wireframe(&AppData::default()).key(a)
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::pipeline::desc::{PipelineDesc, PipelineKey};
+use crate::pipeline::pipeline;
+
+/// Creates graphics pipelines on demand and hands out one pipeline per unique `PipelineDesc`.
+#[derive(Clone, Debug, Default)]
+pub struct PipelineManager {
+    /// The desc and layout of each pipeline are kept to catch key collisions.
+    pipelines: HashMap<PipelineKey, (PipelineDesc, vk::PipelineLayout, vk::Pipeline)>,
+}
+
+impl PipelineManager {
+    pub unsafe fn get(
+        &mut self,
+        device: &Device,
+        layout: vk::PipelineLayout,
+        desc: &PipelineDesc,
+    ) -> Result<vk::Pipeline> {
+        let key = desc.key(layout);
+
+        if let Some((cached_desc, cached_layout, pipeline)) = self.pipelines.get(&key) {
+            if cached_desc != desc || *cached_layout != layout {
+                return Err(anyhow!("Pipeline key {:?} is already in use.", key));
+            }
+
+            return Ok(*pipeline);
+        }
+
+        let pipeline = pipeline::create_graphics_pipeline(device, layout, desc)?;
+        self.pipelines.insert(key, (desc.clone(), layout, pipeline));
+
+        debug!("Pipeline created ({} cached).", self.pipelines.len());
+        Ok(pipeline)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        self.pipelines
+            .drain()
+            .for_each(|(_, (_, _, p))| device.destroy_pipeline(p, None));
+    }
+}
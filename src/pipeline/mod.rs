@@ -1,10 +1,21 @@
 use vulkanalia::prelude::v1_0::*;
 
+use self::manager::PipelineManager;
+use self::view_mode::ViewMode;
+
+pub mod compute;
+pub mod desc;
+pub mod manager;
 pub mod pipeline;
+pub mod view_mode;
 
 #[derive(Clone, Debug, Default)]
 pub struct PipelineData {
     pub render_pass: vk::RenderPass,
     pub pipeline_layout: vk::PipelineLayout,
     pub pipeline: vk::Pipeline,
+    /// Null without a skybox.
+    pub skybox_pipeline: vk::Pipeline,
+    pub pipelines: PipelineManager,
+    pub view_mode: ViewMode,
 }
@@ -1,80 +1,147 @@
+use std::mem::size_of;
+
 use anyhow::Result;
 use log::*;
 use vulkanalia::bytecode::Bytecode;
 use vulkanalia::prelude::v1_0::*;
 
+use crate::drawing::command_buffer;
+use crate::pipeline::desc;
 use crate::texture::image;
-use crate::vertex::vertex;
 use crate::AppData;
 
 pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()> {
-    let vert = include_bytes!("../vertex/shaders/vert.spv");
-    let frag = include_bytes!("../vertex/shaders/frag.spv");
+    let push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+        .offset(0)
+        .size(size_of::<command_buffer::PushConstants>() as u32);
+
+    let set_layouts = &[
+        data.uniform_data.descriptor_set_layout,
+        data.texture_data.textures.descriptor_set_layout,
+    ];
+    let push_constant_ranges = &[push_constant_range];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+
+    data.pipeline_data.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    let layout = data.pipeline_data.pipeline_layout;
+    let active = data.pipeline_data.view_mode.pipeline_desc(data);
+    let transparent = desc::PipelineDesc::transparent(data);
+
+    data.pipeline_data.pipeline = data.pipeline_data.pipelines.get(device, layout, &active)?;
+    data.pipeline_data
+        .pipelines
+        .get(device, layout, &transparent)?;
 
-    let vert_shader_module = create_shader_module(device, &vert[..])?;
-    let frag_shader_module = create_shader_module(device, &frag[..])?;
+    // Recreating the swapchain destroys every pipeline, the skybox's included.
+    if data.texture_data.skybox.is_some() {
+        let skybox = desc::PipelineDesc::skybox(data);
+        data.pipeline_data.skybox_pipeline =
+            data.pipeline_data.pipelines.get(device, layout, &skybox)?;
+    }
+
+    info!("Pipeline created.");
+    Ok(())
+}
+
+pub unsafe fn create_graphics_pipeline(
+    device: &Device,
+    layout: vk::PipelineLayout,
+    desc: &desc::PipelineDesc,
+) -> Result<vk::Pipeline> {
+    let vert_shader_module = create_shader_module(device, desc.shaders.vertex)?;
+    let frag_shader_module = create_shader_module(device, desc.shaders.fragment)?;
 
     let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::VERTEX)
         .module(vert_shader_module)
         .name(b"main\0");
 
+    let map_entries = &[
+        vk::SpecializationMapEntry::builder()
+            .constant_id(0)
+            .offset(0)
+            .size(size_of::<u32>())
+            .build(),
+        vk::SpecializationMapEntry::builder()
+            .constant_id(1)
+            .offset(size_of::<u32>() as u32)
+            .size(size_of::<u32>())
+            .build(),
+    ];
+    let specialization_data = [
+        desc.shaders.specialization,
+        desc.shaders.output_encoding as u32,
+    ]
+    .map(u32::to_ne_bytes)
+    .concat();
+    let specialization_info = vk::SpecializationInfo::builder()
+        .map_entries(map_entries)
+        .data(&specialization_data);
+
     let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::FRAGMENT)
         .module(frag_shader_module)
-        .name(b"main\0");
+        .name(b"main\0")
+        .specialization_info(&specialization_info);
 
-    let binding_descriptions = &[vertex::Vertex::binding_description()];
-    let attribute_descriptions = vertex::Vertex::attribute_descriptions();
     let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-        .vertex_binding_descriptions(binding_descriptions)
-        .vertex_attribute_descriptions(&attribute_descriptions);
+        .vertex_binding_descriptions(&desc.vertex_layout.bindings)
+        .vertex_attribute_descriptions(&desc.vertex_layout.attributes);
 
     let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .topology(desc.raster.topology)
         .primitive_restart_enable(false);
 
-    let viewport = vk::Viewport::builder()
-        .x(0.0)
-        .y(0.0)
-        .width(data.presentation_data.swapchain_extent.width as f32)
-        .height(data.presentation_data.swapchain_extent.height as f32)
-        .min_depth(0.0)
-        .max_depth(1.0);
-
-    let scissor = vk::Rect2D::builder()
-        .offset(vk::Offset2D { x: 0, y: 0 })
-        .extent(data.presentation_data.swapchain_extent);
-
-    let viewports = &[viewport];
-    let scissors = &[scissor];
+    // Viewport and scissor are dynamic, so pipelines survive a resize.
     let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
-        .viewports(viewports)
-        .scissors(scissors);
+        .viewport_count(1)
+        .scissor_count(1);
+
+    let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state =
+        vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
 
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
         .depth_clamp_enable(false)
         .rasterizer_discard_enable(false)
-        .polygon_mode(vk::PolygonMode::FILL)
+        .polygon_mode(desc.raster.polygon_mode)
         .line_width(1.0)
-        .cull_mode(vk::CullModeFlags::BACK)
-        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .cull_mode(desc.raster.cull_mode)
+        .front_face(desc.raster.front_face)
         .depth_bias_enable(false);
 
     let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
-        .sample_shading_enable(true) // Enable sample shading in the pipeline.
+        .sample_shading_enable(desc.render_pass.sample_shading) // Enable sample shading in the pipeline.
         .min_sample_shading(0.2) // Minimum fraction for sample shading; closer to one is smoother.
-        .rasterization_samples(data.texture_data.msaa_samples);
-
-    let attachment = vk::PipelineColorBlendAttachmentState::builder()
-        .color_write_mask(vk::ColorComponentFlags::all())
-        .blend_enable(true)
-        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-        .color_blend_op(vk::BlendOp::ADD)
-        .src_alpha_blend_factor(vk::BlendFactor::ONE)
-        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
-        .alpha_blend_op(vk::BlendOp::ADD);
+        .rasterization_samples(desc.render_pass.samples);
+
+    let attachment = match desc.blend {
+        desc::BlendMode::Opaque => vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(false),
+        desc::BlendMode::Alpha => vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .alpha_blend_op(vk::BlendOp::ADD),
+        desc::BlendMode::Additive => vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .alpha_blend_op(vk::BlendOp::ADD),
+    };
 
     let attachments = &[attachment];
     let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
@@ -83,17 +150,12 @@ pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()>
         .attachments(attachments)
         .blend_constants([0.0, 0.0, 0.0, 0.0]);
 
-    let set_layouts = &[data.uniform_data.descriptor_set_layout];
-    let layout_info = vk::PipelineLayoutCreateInfo::builder().set_layouts(set_layouts);
-
-    data.pipeline_data.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
-
     let stages = &[vert_stage, frag_stage];
 
     let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
-        .depth_test_enable(true)
-        .depth_write_enable(true)
-        .depth_compare_op(vk::CompareOp::LESS)
+        .depth_test_enable(desc.depth_stencil.test)
+        .depth_write_enable(desc.depth_stencil.write)
+        .depth_compare_op(desc.depth_stencil.compare_op)
         .depth_bounds_test_enable(false)
         .min_depth_bounds(0.0) // Optional.
         .max_depth_bounds(1.0) // Optional.
@@ -108,22 +170,22 @@ pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()>
         .multisample_state(&multisample_state)
         .depth_stencil_state(&depth_stencil_state)
         .color_blend_state(&color_blend_state)
-        .layout(data.pipeline_data.pipeline_layout)
-        .render_pass(data.pipeline_data.render_pass)
-        .subpass(0);
+        .dynamic_state(&dynamic_state)
+        .layout(layout)
+        .render_pass(desc.render_pass.render_pass)
+        .subpass(desc.render_pass.subpass);
 
-    data.pipeline_data.pipeline = device
+    let pipeline = device
         .create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)?
         .0[0];
 
     device.destroy_shader_module(vert_shader_module, None);
     device.destroy_shader_module(frag_shader_module, None);
 
-    info!("Pipeline created.");
-    Ok(())
+    Ok(pipeline)
 }
 
-unsafe fn create_shader_module(device: &Device, bytecode: &[u8]) -> Result<vk::ShaderModule> {
+pub unsafe fn create_shader_module(device: &Device, bytecode: &[u8]) -> Result<vk::ShaderModule> {
     let bytecode = Bytecode::new(bytecode).unwrap();
 
     let info = vk::ShaderModuleCreateInfo::builder()
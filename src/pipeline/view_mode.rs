@@ -0,0 +1,74 @@
+use vulkanalia::prelude::v1_0::*;
+use winit::event::VirtualKeyCode;
+
+use crate::pipeline::desc::{PipelineDesc, RasterDesc, ShaderDesc, SHADER_VERT};
+use crate::AppData;
+
+pub const SHADER_DEBUG_FRAG: &[u8] = include_bytes!("../vertex/shaders/debug_frag.spv");
+
+/// How the scene is shaded, switchable at runtime for diagnosing assets.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ViewMode {
+    #[default]
+    Shaded,
+    Wireframe,
+    Normals,
+    UvChecker,
+    MipLevel,
+    Depth,
+    VertexColor,
+}
+
+impl ViewMode {
+    pub fn from_key(key: VirtualKeyCode) -> Option<Self> {
+        match key {
+            VirtualKeyCode::Key1 => Some(Self::Shaded),
+            VirtualKeyCode::Key2 => Some(Self::Wireframe),
+            VirtualKeyCode::Key3 => Some(Self::Normals),
+            VirtualKeyCode::Key4 => Some(Self::UvChecker),
+            VirtualKeyCode::Key5 => Some(Self::MipLevel),
+            VirtualKeyCode::Key6 => Some(Self::Depth),
+            VirtualKeyCode::Key7 => Some(Self::VertexColor),
+            _ => None,
+        }
+    }
+
+    /// The value of the `VIEW_MODE` specialization constant in `debug.frag`.
+    fn debug_constant(self) -> Option<u32> {
+        match self {
+            Self::Shaded | Self::Wireframe => None,
+            Self::Normals => Some(1),
+            Self::UvChecker => Some(2),
+            Self::MipLevel => Some(3),
+            Self::Depth => Some(4),
+            Self::VertexColor => Some(5),
+        }
+    }
+
+    pub fn is_supported(self, data: &AppData) -> bool {
+        self != Self::Wireframe || data.setup_data.features.fill_mode_non_solid == vk::TRUE
+    }
+
+    pub fn pipeline_desc(self, data: &AppData) -> PipelineDesc {
+        if self == Self::Wireframe {
+            return PipelineDesc::wireframe(data);
+        }
+
+        match self.debug_constant() {
+            Some(constant) => PipelineDesc {
+                shaders: ShaderDesc {
+                    vertex: SHADER_VERT,
+                    fragment: SHADER_DEBUG_FRAG,
+                    specialization: constant,
+                    output_encoding: data.presentation_data.output_encoding,
+                },
+                raster: RasterDesc {
+                    cull_mode: vk::CullModeFlags::NONE,
+                    ..Default::default()
+                },
+                ..PipelineDesc::opaque(data)
+            },
+            None => PipelineDesc::opaque(data),
+        }
+    }
+}
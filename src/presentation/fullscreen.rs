@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use log::*;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::{MonitorHandle, VideoMode};
+use winit::window::{Fullscreen, Window};
+
+use crate::AppData;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    /// A borderless window covering the monitor at its current video mode.
+    Borderless,
+    /// Exclusive fullscreen, switching the monitor's video mode.
+    Exclusive,
+}
+
+impl FullscreenMode {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "windowed" => Ok(FullscreenMode::Windowed),
+            "borderless" => Ok(FullscreenMode::Borderless),
+            "exclusive" => Ok(FullscreenMode::Exclusive),
+            _ => Err(anyhow!(
+                "Unknown fullscreen mode `{}`, expected `windowed`, `borderless` or `exclusive`.",
+                value
+            )),
+        }
+    }
+}
+
+/// A video mode asked for on the command line, as `WIDTHxHEIGHT` or `WIDTHxHEIGHT@HZ`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VideoModeRequest {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_hz: Option<u32>,
+}
+
+impl VideoModeRequest {
+    pub fn parse(value: &str) -> Result<Self> {
+        let error = || {
+            anyhow!(
+                "Invalid video mode `{}`, expected e.g. `1920x1080@60`.",
+                value
+            )
+        };
+
+        let (size, refresh_hz) = match value.split_once('@') {
+            Some((size, hz)) => (size, Some(hz.parse().map_err(|_| error())?)),
+            None => (value, None),
+        };
+
+        let (width, height) = size.split_once('x').ok_or_else(error)?;
+
+        Ok(Self {
+            width: width.parse().map_err(|_| error())?,
+            height: height.parse().map_err(|_| error())?,
+            refresh_hz,
+        })
+    }
+}
+
+/// The current fullscreen state and the windowed geometry to restore.
+#[derive(Clone, Debug, Default)]
+pub struct FullscreenData {
+    pub mode: FullscreenMode,
+    pub windowed_size: Option<PhysicalSize<u32>>,
+    pub windowed_position: Option<PhysicalPosition<i32>>,
+}
+
+/// Switches between windowed and fullscreen.
+///
+/// Fullscreen is exclusive if that was asked for on the command line, borderless otherwise.
+pub fn toggle_fullscreen(window: &Window, data: &mut AppData) {
+    let mode = match data.presentation_data.fullscreen.mode {
+        FullscreenMode::Windowed if data.config.fullscreen == FullscreenMode::Exclusive => {
+            FullscreenMode::Exclusive
+        }
+        FullscreenMode::Windowed => FullscreenMode::Borderless,
+        _ => FullscreenMode::Windowed,
+    };
+
+    set_fullscreen(window, data, mode);
+}
+
+/// Changes the fullscreen mode; the resulting resize recreates the swapchain as usual.
+pub fn set_fullscreen(window: &Window, data: &mut AppData, mode: FullscreenMode) {
+    let fullscreen = &mut data.presentation_data.fullscreen;
+    if fullscreen.mode == mode {
+        return;
+    }
+
+    if fullscreen.mode == FullscreenMode::Windowed {
+        fullscreen.windowed_size = Some(window.inner_size());
+        fullscreen.windowed_position = window.outer_position().ok();
+    }
+
+    let monitor = get_monitor(window, data.config.monitor);
+
+    match mode {
+        FullscreenMode::Windowed => {
+            window.set_fullscreen(None);
+
+            let fullscreen = &data.presentation_data.fullscreen;
+            if let Some(size) = fullscreen.windowed_size {
+                window.set_inner_size(size);
+            }
+            if let Some(position) = fullscreen.windowed_position {
+                window.set_outer_position(position);
+            }
+        }
+        FullscreenMode::Borderless => {
+            window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+        }
+        FullscreenMode::Exclusive => {
+            let video_mode = monitor
+                .as_ref()
+                .and_then(|m| get_video_mode(m, data.config.video_mode));
+
+            match video_mode {
+                Some(video_mode) => {
+                    info!(
+                        "Using video mode {}x{} @ {:.2} Hz.",
+                        video_mode.size().width,
+                        video_mode.size().height,
+                        video_mode.refresh_rate_millihertz() as f32 / 1000.0
+                    );
+                    window.set_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
+                }
+                None => {
+                    warn!("No video mode available for exclusive fullscreen, using borderless.");
+                    window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+                }
+            }
+        }
+    }
+
+    data.presentation_data.fullscreen.mode = mode;
+    info!("Fullscreen mode set to {:?}.", mode);
+}
+
+/// The monitor at `index` in `available_monitors`, or the window's current monitor.
+fn get_monitor(window: &Window, index: Option<usize>) -> Option<MonitorHandle> {
+    if let Some(index) = index {
+        match window.available_monitors().nth(index) {
+            Some(monitor) => return Some(monitor),
+            None => warn!("Monitor {} not found, using the current monitor.", index),
+        }
+    }
+
+    window
+        .current_monitor()
+        .or_else(|| window.primary_monitor())
+}
+
+/// The requested video mode if the monitor has it, otherwise its largest and fastest one.
+fn get_video_mode(monitor: &MonitorHandle, request: Option<VideoModeRequest>) -> Option<VideoMode> {
+    let matches = |m: &VideoMode, r: VideoModeRequest| {
+        let hz = (m.refresh_rate_millihertz() + 500) / 1000;
+        m.size().width == r.width
+            && m.size().height == r.height
+            && r.refresh_hz.is_none_or(|r| r == hz)
+    };
+
+    let rank = |m: &VideoMode| {
+        let size = m.size();
+        (
+            size.width * size.height,
+            m.refresh_rate_millihertz(),
+            m.bit_depth(),
+        )
+    };
+
+    if let Some(request) = request {
+        let requested = monitor
+            .video_modes()
+            .filter(|m| matches(m, request))
+            .max_by_key(rank);
+
+        if requested.is_some() {
+            return requested;
+        }
+
+        warn!("Video mode {:?} not available on this monitor.", request);
+    }
+
+    monitor.video_modes().max_by_key(rank)
+}
@@ -1,12 +1,28 @@
 use vulkanalia::prelude::v1_0::*;
 
+use self::fullscreen::FullscreenData;
+use self::present_mode::PresentModeSetting;
+use self::surface_format::OutputEncoding;
+use self::swapchain::RetiredSwapchain;
+
+pub mod fullscreen;
+pub mod present_mode;
+pub mod surface_format;
 pub mod swapchain;
+pub mod window;
 
 #[derive(Clone, Debug, Default)]
 pub struct PresentationData {
     pub swapchain_format: vk::Format,
+    pub color_space: vk::ColorSpaceKHR,
+    pub output_encoding: OutputEncoding,
     pub swapchain_extent: vk::Extent2D,
+    /// The present mode asked for, which `present_mode` may have fallen back from.
+    pub present_mode_setting: PresentModeSetting,
+    pub present_mode: vk::PresentModeKHR,
     pub swapchain: vk::SwapchainKHR,
     pub swapchain_images: Vec<vk::Image>,
     pub swapchain_image_views: Vec<vk::ImageView>,
-}
\ No newline at end of file
+    pub retired_swapchains: Vec<RetiredSwapchain>,
+    pub fullscreen: FullscreenData,
+}
@@ -0,0 +1,71 @@
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+/// The presentation behavior asked for, mapped onto a Vulkan present mode.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PresentModeSetting {
+    /// `FIFO`, always supported.
+    Vsync,
+    /// `FIFO_RELAXED`, which tears instead of waiting when a frame is late.
+    Adaptive,
+    /// `MAILBOX`.
+    #[default]
+    LowLatency,
+    /// `IMMEDIATE`, which may tear.
+    Uncapped,
+}
+
+impl PresentModeSetting {
+    pub const ALL: [PresentModeSetting; 4] = [
+        PresentModeSetting::Vsync,
+        PresentModeSetting::Adaptive,
+        PresentModeSetting::LowLatency,
+        PresentModeSetting::Uncapped,
+    ];
+
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "vsync" | "fifo" => Ok(PresentModeSetting::Vsync),
+            "adaptive" | "fifo-relaxed" => Ok(PresentModeSetting::Adaptive),
+            "low-latency" | "mailbox" => Ok(PresentModeSetting::LowLatency),
+            "uncapped" | "immediate" => Ok(PresentModeSetting::Uncapped),
+            _ => Err(anyhow!(
+                "Unknown present mode `{}`, expected `vsync`, `adaptive`, `low-latency` or `uncapped`.",
+                value
+            )),
+        }
+    }
+
+    pub fn present_mode(self) -> vk::PresentModeKHR {
+        match self {
+            PresentModeSetting::Vsync => vk::PresentModeKHR::FIFO,
+            PresentModeSetting::Adaptive => vk::PresentModeKHR::FIFO_RELAXED,
+            PresentModeSetting::LowLatency => vk::PresentModeKHR::MAILBOX,
+            PresentModeSetting::Uncapped => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+
+    /// The setting after this one, for cycling through them with a hotkey.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|s| *s == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Picks the present mode for a setting, falling back to `FIFO` if it's unsupported.
+pub fn get_present_mode(
+    present_modes: &[vk::PresentModeKHR],
+    setting: PresentModeSetting,
+) -> vk::PresentModeKHR {
+    let present_mode = setting.present_mode();
+    if present_modes.contains(&present_mode) {
+        present_mode
+    } else {
+        warn!(
+            "Present mode {:?} ({:?}) is not supported, falling back to FIFO.",
+            setting, present_mode
+        );
+        vk::PresentModeKHR::FIFO
+    }
+}
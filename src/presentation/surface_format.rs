@@ -0,0 +1,131 @@
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+/// How the fragment shaders encode linear color for the swapchain, passed to them as
+/// specialization constant 1.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputEncoding {
+    /// Written as is, for `_SRGB` formats that encode on store.
+    #[default]
+    Hardware = 0,
+    /// sRGB transfer function applied in the shader, for `UNORM` formats.
+    Srgb = 1,
+    /// BT.2020 primaries and the PQ transfer function.
+    Hdr10 = 2,
+    /// Linear with 1.0 at 80 nits, for floating-point formats.
+    ScRgb = 3,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SurfaceFormatPreference {
+    format: vk::Format,
+    color_space: vk::ColorSpaceKHR,
+    encoding: OutputEncoding,
+    /// Whether the color space needs `VK_EXT_swapchain_colorspace`.
+    extended: bool,
+}
+
+const fn preference(
+    format: vk::Format,
+    color_space: vk::ColorSpaceKHR,
+    encoding: OutputEncoding,
+    extended: bool,
+) -> SurfaceFormatPreference {
+    SurfaceFormatPreference {
+        format,
+        color_space,
+        encoding,
+        extended,
+    }
+}
+
+/// Formats tried in order when HDR output is requested, before the SDR ones.
+const HDR_PREFERENCES: &[SurfaceFormatPreference] = &[
+    preference(
+        vk::Format::R16G16B16A16_SFLOAT,
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        OutputEncoding::ScRgb,
+        true,
+    ),
+    preference(
+        vk::Format::A2B10G10R10_UNORM_PACK32,
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+        OutputEncoding::Hdr10,
+        true,
+    ),
+    preference(
+        vk::Format::A2R10G10B10_UNORM_PACK32,
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+        OutputEncoding::Hdr10,
+        true,
+    ),
+    preference(
+        vk::Format::A2B10G10R10_UNORM_PACK32,
+        vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        OutputEncoding::Srgb,
+        false,
+    ),
+];
+
+const SDR_PREFERENCES: &[SurfaceFormatPreference] = &[
+    preference(
+        vk::Format::B8G8R8A8_SRGB,
+        vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        OutputEncoding::Hardware,
+        false,
+    ),
+    preference(
+        vk::Format::R8G8B8A8_SRGB,
+        vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        OutputEncoding::Hardware,
+        false,
+    ),
+];
+
+const SRGB_FORMATS: &[vk::Format] = &[
+    vk::Format::B8G8R8A8_SRGB,
+    vk::Format::R8G8B8A8_SRGB,
+    vk::Format::A8B8G8R8_SRGB_PACK32,
+];
+
+/// Picks the highest ranked supported surface format and how to encode output for it.
+///
+/// HDR and wide-gamut formats are only considered if `hdr` is set, and extended color spaces
+/// only if `VK_EXT_swapchain_colorspace` is enabled.
+pub fn get_surface_format(
+    formats: &[vk::SurfaceFormatKHR],
+    hdr: bool,
+    colorspace_extension: bool,
+) -> (vk::SurfaceFormatKHR, OutputEncoding) {
+    let preferences = if hdr { HDR_PREFERENCES } else { &[] };
+
+    let preferred = preferences
+        .iter()
+        .chain(SDR_PREFERENCES)
+        .filter(|p| colorspace_extension || !p.extended)
+        .find_map(|p| {
+            formats
+                .iter()
+                .find(|f| f.format == p.format && f.color_space == p.color_space)
+                .map(|f| (*f, p.encoding))
+        });
+
+    if let Some(preferred) = preferred {
+        return preferred;
+    }
+
+    let format = formats[0];
+    warn!(
+        "No preferred surface format is supported, using {:?} ({:?}).",
+        format.format, format.color_space
+    );
+
+    let encoding = if SRGB_FORMATS.contains(&format.format) {
+        OutputEncoding::Hardware
+    } else {
+        OutputEncoding::Srgb
+    };
+
+    (format, encoding)
+}
@@ -1,14 +1,17 @@
-use anyhow::Result;
+use std::mem;
+
+use anyhow::{anyhow, Result};
 use log::*;
 use vulkanalia::prelude::v1_0::*;
 use vulkanalia::vk::{KhrSurfaceExtension, KhrSwapchainExtension};
 use winit::window::Window;
 
-use crate::drawing::{command_buffer, frame_buffer};
+use crate::drawing::frame_buffer;
+use crate::pipeline::manager::PipelineManager;
 use crate::pipeline::pipeline;
+use crate::presentation::{present_mode, surface_format};
 use crate::setup::device::queue_families;
 use crate::texture::image;
-use crate::uniform::descriptor;
 use crate::{App, AppData};
 
 pub const DEVICE_EXTENSIONS: &[vk::ExtensionName] = &[vk::KHR_SWAPCHAIN_EXTENSION.name];
@@ -23,39 +26,19 @@ pub struct SwapchainSupport {
 impl SwapchainSupport {
     pub unsafe fn get(
         instance: &Instance,
-        data: &AppData,
         physical_device: vk::PhysicalDevice,
+        surface: vk::SurfaceKHR,
     ) -> Result<Self> {
         Ok(Self {
             capabilities: instance
-                .get_physical_device_surface_capabilities_khr(physical_device, data.surface)?,
-            formats: instance
-                .get_physical_device_surface_formats_khr(physical_device, data.surface)?,
+                .get_physical_device_surface_capabilities_khr(physical_device, surface)?,
+            formats: instance.get_physical_device_surface_formats_khr(physical_device, surface)?,
             present_modes: instance
-                .get_physical_device_surface_present_modes_khr(physical_device, data.surface)?,
+                .get_physical_device_surface_present_modes_khr(physical_device, surface)?,
         })
     }
 }
 
-fn get_swapchain_surface_format(formats: &[vk::SurfaceFormatKHR]) -> vk::SurfaceFormatKHR {
-    formats
-        .iter()
-        .cloned()
-        .find(|f| {
-            f.format == vk::Format::B8G8R8A8_SRGB
-                && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-        })
-        .unwrap_or_else(|| formats[0])
-}
-
-fn get_swapchain_present_mode(present_modes: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
-    present_modes
-        .iter()
-        .cloned()
-        .find(|m| *m == vk::PresentModeKHR::MAILBOX)
-        .unwrap_or(vk::PresentModeKHR::FIFO)
-}
-
 fn get_swapchain_extent(window: &Window, capabilities: vk::SurfaceCapabilitiesKHR) -> vk::Extent2D {
     if capabilities.current_extent.width != u32::MAX {
         capabilities.current_extent
@@ -83,14 +66,24 @@ pub unsafe fn create_swapchain(
     device: &Device,
     data: &mut AppData,
 ) -> Result<()> {
-    let support = SwapchainSupport::get(instance, data, data.setup_data.physical_device)?;
+    let support = SwapchainSupport::get(instance, data.setup_data.physical_device, data.surface)?;
 
-    let surface_format = get_swapchain_surface_format(&support.formats);
-    let present_mode = get_swapchain_present_mode(&support.present_modes);
+    let (surface_format, output_encoding) = surface_format::get_surface_format(
+        &support.formats,
+        data.config.hdr,
+        data.setup_data.swapchain_colorspace,
+    );
+    let present_mode = present_mode::get_present_mode(
+        &support.present_modes,
+        data.presentation_data.present_mode_setting,
+    );
     let extent = get_swapchain_extent(window, support.capabilities);
 
     data.presentation_data.swapchain_format = surface_format.format;
+    data.presentation_data.color_space = surface_format.color_space;
+    data.presentation_data.output_encoding = output_encoding;
     data.presentation_data.swapchain_extent = extent;
+    data.presentation_data.present_mode = present_mode;
 
     let mut image_count = support.capabilities.min_image_count + 1;
     if support.capabilities.max_image_count != 0
@@ -126,13 +119,24 @@ pub unsafe fn create_swapchain(
         .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
         .present_mode(present_mode)
         .clipped(true)
-        .old_swapchain(vk::SwapchainKHR::null());
+        .old_swapchain(data.presentation_data.swapchain);
+
+    let swapchain = device.create_swapchain_khr(&info, None)?;
+    let images = match device.get_swapchain_images_khr(swapchain) {
+        Ok(images) => images,
+        Err(e) => {
+            device.destroy_swapchain_khr(swapchain, None);
+            return Err(anyhow!(e));
+        }
+    };
 
-    data.presentation_data.swapchain = device.create_swapchain_khr(&info, None)?;
-    data.presentation_data.swapchain_images =
-        device.get_swapchain_images_khr(data.presentation_data.swapchain)?;
+    data.presentation_data.swapchain = swapchain;
+    data.presentation_data.swapchain_images = images;
 
-    info!("Swapchain created.");
+    info!(
+        "Swapchain created ({:?}, {:?}, {:?}).",
+        surface_format.format, surface_format.color_space, present_mode
+    );
     Ok(())
 }
 
@@ -148,6 +152,7 @@ pub unsafe fn create_swapchain_image_views(device: &Device, data: &mut AppData)
                 data.presentation_data.swapchain_format,
                 vk::ImageAspectFlags::COLOR,
                 1,
+                vk::ImageViewType::_2D,
             )
         })
         .collect::<Result<Vec<_>, _>>()?;
@@ -155,15 +160,49 @@ pub unsafe fn create_swapchain_image_views(device: &Device, data: &mut AppData)
     Ok(())
 }
 
+/// Recreates the swapchain without waiting for the device to idle.
+///
+/// The old swapchain is handed to the new one, and the resources that were created for it
+/// are retired until the frames submitted with them have completed.
 pub unsafe fn recreate_swapchain(app: &mut App, window: &Window) -> Result<()> {
-    app.device.device_wait_idle()?;
+    let format = app.data.presentation_data.swapchain_format;
+    let encoding = app.data.presentation_data.output_encoding;
+    let retired = RetiredSwapchain::take(&mut app.data);
+
+    // The old swapchain stays current if no new one was created, so it is destroyed once with
+    // the window rather than also as a retired swapchain.
+    if let Err(e) = create_swapchain(window, &app.instance, &app.device, &mut app.data) {
+        retired.restore(&mut app.data);
+        return Err(e);
+    }
+
+    app.data.presentation_data.retired_swapchains.push(retired);
 
-    destroy_swapchain(app);
-    create_swapchain(window, &app.instance, &app.device, &mut app.data)?;
     create_swapchain_image_views(&app.device, &mut app.data)?;
 
-    pipeline::create_render_pass(&app.instance, &app.device, &mut app.data)?;
-    pipeline::create_pipeline(&app.device, &mut app.data)?;
+    // Pipelines use a dynamic viewport, so they only depend on the swapchain's format and
+    // the output encoding that comes with it.
+    if app.data.presentation_data.swapchain_format != format
+        || app.data.presentation_data.output_encoding != encoding
+    {
+        // The render pass and pipelines are shared, so every window must keep one format.
+        if app.data.windows.len() > 1 {
+            return Err(anyhow!(
+                "Swapchain format changed to {:?}, but the render pass is shared with other windows.",
+                app.data.presentation_data.swapchain_format
+            ));
+        }
+
+        let mut retired = RetiredSwapchain {
+            retired_at: app.data.drawing_data.frame_count,
+            ..Default::default()
+        };
+        retired.take_pipelines(&mut app.data);
+        app.data.presentation_data.retired_swapchains.push(retired);
+
+        pipeline::create_render_pass(&app.instance, &app.device, &mut app.data)?;
+        pipeline::create_pipeline(&app.device, &mut app.data)?;
+    }
 
     image::create_color_objects(&app.instance, &app.device, &mut app.data)?;
 
@@ -171,71 +210,122 @@ pub unsafe fn recreate_swapchain(app: &mut App, window: &Window) -> Result<()> {
 
     frame_buffer::create_framebuffers(&app.device, &mut app.data)?;
 
-    descriptor::create_uniform_buffers(&app.instance, &app.device, &mut app.data)?;
-    descriptor::create_descriptor_pool(&app.device, &mut app.data)?;
-    descriptor::create_descriptor_sets(&app.device, &mut app.data)?;
-
-    command_buffer::create_command_buffers(&app.device, &mut app.data)?;
+    // The image count may have changed, and none of the new images has been acquired yet.
+    app.data.drawing_data.images_in_flight =
+        vec![vk::Fence::null(); app.data.presentation_data.swapchain_images.len()];
 
     info!("Swapchain re-created.");
     Ok(())
 }
 
+/// Destroys retired swapchains whose frames have all completed.
+///
+/// `completed_frames` is the number of submitted frames known to have finished.
+pub unsafe fn destroy_retired_swapchains(
+    device: &Device,
+    data: &mut AppData,
+    completed_frames: u64,
+) {
+    let (completed, pending) = mem::take(&mut data.presentation_data.retired_swapchains)
+        .into_iter()
+        .partition::<Vec<_>, _>(|r| r.retired_at <= completed_frames);
+
+    data.presentation_data.retired_swapchains = pending;
+    completed.into_iter().for_each(|r| r.destroy(device));
+}
+
+/// Destroys the current window's swapchain resources, but not the shared pipelines.
 pub unsafe fn destroy_swapchain(app: &mut App) {
-    app.device
-        .destroy_image_view(app.data.texture_data.color_image_view, None);
-    app.device
-        .free_memory(app.data.texture_data.color_image_memory, None);
-    app.device
-        .destroy_image(app.data.texture_data.color_image, None);
-
-    app.device
-        .destroy_image_view(app.data.texture_data.depth_image_view, None);
-    app.device
-        .free_memory(app.data.texture_data.depth_image_memory, None);
-    app.device
-        .destroy_image(app.data.texture_data.depth_image, None);
-
-    app.device
-        .destroy_descriptor_pool(app.data.uniform_data.descriptor_pool, None);
+    RetiredSwapchain::take(&mut app.data).destroy(&app.device);
 
     app.data
-        .uniform_data
-        .uniform_buffers
-        .iter()
-        .for_each(|b| app.device.destroy_buffer(*b, None));
-    app.data
-        .uniform_data
-        .uniform_buffers_memory
-        .iter()
-        .for_each(|m| app.device.free_memory(*m, None));
+        .presentation_data
+        .retired_swapchains
+        .drain(..)
+        .for_each(|r| r.destroy(&app.device));
+}
 
-    app.data
-        .drawing_data
-        .framebuffers
-        .iter()
-        .for_each(|f| app.device.destroy_framebuffer(*f, None));
+/// Resources created for a swapchain that has since been replaced.
+#[derive(Clone, Debug, Default)]
+pub struct RetiredSwapchain {
+    /// Frames submitted before the swapchain was replaced, any of which may still use it.
+    pub retired_at: u64,
+    pub swapchain: vk::SwapchainKHR,
+    pub image_views: Vec<vk::ImageView>,
+    pub framebuffers: Vec<vk::Framebuffer>,
+    pub color_image: vk::Image,
+    pub color_image_memory: vk::DeviceMemory,
+    pub color_image_view: vk::ImageView,
+    pub depth_image: vk::Image,
+    pub depth_image_memory: vk::DeviceMemory,
+    pub depth_image_view: vk::ImageView,
+    pub render_pass: vk::RenderPass,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipelines: PipelineManager,
+}
 
-    app.device.free_command_buffers(
-        app.data.drawing_data.command_pool,
-        &app.data.drawing_data.command_buffers,
-    );
+impl RetiredSwapchain {
+    /// Takes the current swapchain resources, leaving the swapchain handle in place so it can
+    /// be passed as the old swapchain.
+    ///
+    /// The taken handles are nulled, so a failed recreation never destroys them twice.
+    fn take(data: &mut AppData) -> Self {
+        Self {
+            retired_at: data.drawing_data.frame_count,
+            swapchain: data.presentation_data.swapchain,
+            image_views: mem::take(&mut data.presentation_data.swapchain_image_views),
+            framebuffers: mem::take(&mut data.drawing_data.framebuffers),
+            color_image: mem::take(&mut data.texture_data.color_image),
+            color_image_memory: mem::take(&mut data.texture_data.color_image_memory),
+            color_image_view: mem::take(&mut data.texture_data.color_image_view),
+            depth_image: mem::take(&mut data.texture_data.depth_image),
+            depth_image_memory: mem::take(&mut data.texture_data.depth_image_memory),
+            depth_image_view: mem::take(&mut data.texture_data.depth_image_view),
+            ..Default::default()
+        }
+    }
 
-    app.device
-        .destroy_pipeline(app.data.pipeline_data.pipeline, None);
+    /// Puts back the resources taken by `take`.
+    fn restore(self, data: &mut AppData) {
+        data.presentation_data.swapchain_image_views = self.image_views;
+        data.drawing_data.framebuffers = self.framebuffers;
+        data.texture_data.color_image = self.color_image;
+        data.texture_data.color_image_memory = self.color_image_memory;
+        data.texture_data.color_image_view = self.color_image_view;
+        data.texture_data.depth_image = self.depth_image;
+        data.texture_data.depth_image_memory = self.depth_image_memory;
+        data.texture_data.depth_image_view = self.depth_image_view;
+    }
 
-    app.device
-        .destroy_pipeline_layout(app.data.pipeline_data.pipeline_layout, None);
+    fn take_pipelines(&mut self, data: &mut AppData) {
+        self.render_pass = data.pipeline_data.render_pass;
+        self.pipeline_layout = data.pipeline_data.pipeline_layout;
+        self.pipelines = mem::take(&mut data.pipeline_data.pipelines);
+    }
 
-    app.device
-        .destroy_render_pass(app.data.pipeline_data.render_pass, None);
+    unsafe fn destroy(mut self, device: &Device) {
+        device.destroy_image_view(self.color_image_view, None);
+        device.free_memory(self.color_image_memory, None);
+        device.destroy_image(self.color_image, None);
 
-    app.data
-        .presentation_data
-        .swapchain_image_views
-        .iter()
-        .for_each(|v| app.device.destroy_image_view(*v, None));
+        device.destroy_image_view(self.depth_image_view, None);
+        device.free_memory(self.depth_image_memory, None);
+        device.destroy_image(self.depth_image, None);
+
+        self.framebuffers
+            .iter()
+            .for_each(|f| device.destroy_framebuffer(*f, None));
 
-    app.device
-        .destroy_swapchain_khr(app.data.presentation_data.swapchain, None);
+        self.pipelines.destroy(device);
+
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+
+        device.destroy_render_pass(self.render_pass, None);
+
+        self.image_views
+            .iter()
+            .for_each(|v| device.destroy_image_view(*v, None));
+
+        device.destroy_swapchain_khr(self.swapchain, None);
+    }
 }
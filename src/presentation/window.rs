@@ -0,0 +1,149 @@
+use std::mem;
+
+use anyhow::{anyhow, Result};
+use vulkanalia::prelude::v1_0::*;
+use winit::window::Window;
+
+use crate::drawing::frame_buffer;
+use crate::drawing::viewport::ViewportLayout;
+use crate::presentation::{swapchain, PresentationData};
+use crate::scene::camera::Camera;
+use crate::scene::SceneData;
+use crate::texture::image;
+use crate::AppData;
+
+/// What a window shows.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WindowRole {
+    /// The whole scene.
+    #[default]
+    Scene,
+    /// The material of the first scene object, on a single object close up.
+    MaterialPreview,
+}
+
+/// The state of one window, which has its own surface, swapchain and frames in flight and
+/// shares the device and everything else.
+///
+/// The swapchain code works on the window state in `AppData`, so a window's state is swapped
+/// into `AppData` with `swap` while it is rendered, resized or destroyed.
+#[derive(Clone, Debug, Default)]
+pub struct WindowData {
+    pub role: WindowRole,
+    /// The first of this window's frames in `DrawingData::frames`.
+    pub first_frame: usize,
+    /// The frame in `DrawingData::frames` this window renders next.
+    pub frame: usize,
+    pub resized: bool,
+    pub minimized: bool,
+    pub camera: Camera,
+    pub viewport_layout: ViewportLayout,
+    /// What the window draws instead of the app's scene, built once when the scene is created.
+    pub scene: Option<SceneData>,
+    pub surface: vk::SurfaceKHR,
+    pub presentation_data: PresentationData,
+    pub framebuffers: Vec<vk::Framebuffer>,
+    pub images_in_flight: Vec<vk::Fence>,
+    /// Frames this window has submitted so far.
+    pub frame_count: u64,
+    pub depth_image: vk::Image,
+    pub depth_image_memory: vk::DeviceMemory,
+    pub depth_image_view: vk::ImageView,
+    pub color_image: vk::Image,
+    pub color_image_memory: vk::DeviceMemory,
+    pub color_image_view: vk::ImageView,
+}
+
+impl WindowData {
+    pub fn new(role: WindowRole, first_frame: usize) -> Self {
+        let camera = match role {
+            WindowRole::Scene => Camera::default(),
+            WindowRole::MaterialPreview => Camera::preview(),
+        };
+
+        Self {
+            role,
+            first_frame,
+            frame: first_frame,
+            camera,
+            ..Default::default()
+        }
+    }
+
+    /// Exchanges this window's state with the window state in `data`.
+    pub fn swap(&mut self, data: &mut AppData) {
+        mem::swap(&mut self.camera, &mut data.camera);
+        mem::swap(&mut self.viewport_layout, &mut data.viewport_layout);
+        mem::swap(&mut self.surface, &mut data.surface);
+        mem::swap(&mut self.presentation_data, &mut data.presentation_data);
+
+        let drawing = &mut data.drawing_data;
+        mem::swap(&mut self.framebuffers, &mut drawing.framebuffers);
+        mem::swap(&mut self.images_in_flight, &mut drawing.images_in_flight);
+        mem::swap(&mut self.frame_count, &mut drawing.frame_count);
+
+        let texture = &mut data.texture_data;
+        mem::swap(&mut self.depth_image, &mut texture.depth_image);
+        mem::swap(
+            &mut self.depth_image_memory,
+            &mut texture.depth_image_memory,
+        );
+        mem::swap(&mut self.depth_image_view, &mut texture.depth_image_view);
+        mem::swap(&mut self.color_image, &mut texture.color_image);
+        mem::swap(
+            &mut self.color_image_memory,
+            &mut texture.color_image_memory,
+        );
+        mem::swap(&mut self.color_image_view, &mut texture.color_image_view);
+    }
+}
+
+/// Exchanges the state of the window at `index` with the window state in `data`.
+pub fn swap_window(data: &mut AppData, index: usize) {
+    let mut window = mem::take(&mut data.windows[index]);
+    window.swap(data);
+    data.windows[index] = window;
+}
+
+/// Creates the swapchain and attachments of a window after the first, whose format the shared
+/// render pass and pipelines were created for.
+pub unsafe fn create_window_swapchain(
+    window: &Window,
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    swapchain::create_swapchain(window, instance, device, data)?;
+    swapchain::create_swapchain_image_views(device, data)?;
+
+    let first = &data.windows[0].presentation_data;
+    if data.presentation_data.swapchain_format != first.swapchain_format
+        || data.presentation_data.output_encoding != first.output_encoding
+    {
+        return Err(anyhow!(
+            "Window surface format {:?} doesn't match the first window's {:?}.",
+            data.presentation_data.swapchain_format,
+            first.swapchain_format
+        ));
+    }
+
+    image::create_color_objects(instance, device, data)?;
+    image::create_depth_objects(instance, device, data)?;
+
+    frame_buffer::create_framebuffers(device, data)?;
+
+    data.drawing_data.images_in_flight =
+        vec![vk::Fence::null(); data.presentation_data.swapchain_images.len()];
+
+    Ok(())
+}
+
+/// The surface of every window, whether or not its state is swapped into `data`.
+pub fn get_surfaces(data: &AppData) -> Vec<vk::SurfaceKHR> {
+    data.windows
+        .iter()
+        .map(|w| w.surface)
+        .chain([data.surface])
+        .filter(|s| !s.is_null())
+        .collect()
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::profiling::timestamp::{self, GpuPass};
+use crate::AppData;
+
+/// The fixed simulation rate of benchmarks, so every run animates the same frames.
+const BENCH_FRAME_RATE: f32 = 60.0;
+/// How many objects the benchmark scene places on the grid.
+pub const BENCH_OBJECTS: usize = 64;
+
+/// Frame times collected by `--bench`.
+///
+/// Benchmarks render to the window like any other run; there is no headless mode, so the
+/// present mode and compositor are part of what is measured.
+#[derive(Clone, Debug)]
+pub struct Bench {
+    pub frames: u32,
+    pub warmup_frames: u32,
+    pub json_path: Option<PathBuf>,
+    /// Frames submitted so far, including warm-up frames.
+    pub rendered: u32,
+    /// The benchmark frame each frame in flight last submitted, until its GPU time is read.
+    pub submitted: Vec<Option<u32>>,
+    pub cpu_ms: Vec<f32>,
+    pub gpu_ms: Vec<f32>,
+}
+
+impl Bench {
+    pub fn new(
+        frames: u32,
+        warmup_frames: u32,
+        json_path: Option<PathBuf>,
+        frame_slots: usize,
+    ) -> Self {
+        Self {
+            frames,
+            warmup_frames,
+            json_path,
+            rendered: 0,
+            submitted: vec![None; frame_slots],
+            cpu_ms: Vec::with_capacity(frames as usize),
+            gpu_ms: Vec::with_capacity(frames as usize),
+        }
+    }
+
+    /// Whether the given benchmark frame is measured rather than warming up.
+    fn measured(&self, index: u32) -> bool {
+        (self.warmup_frames..self.warmup_frames + self.frames).contains(&index)
+    }
+
+    /// The animation time of the current frame.
+    pub fn time(&self) -> f32 {
+        self.rendered as f32 / BENCH_FRAME_RATE
+    }
+
+    /// One frame past the last measured one is rendered, since a frame's CPU time is only
+    /// known once the next one starts.
+    pub fn finished(&self) -> bool {
+        self.rendered > self.warmup_frames + self.frames
+    }
+}
+
+/// Summary statistics of a set of frame times, in milliseconds.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameTimeStats {
+    pub min: f32,
+    pub avg: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+impl FrameTimeStats {
+    pub fn new(samples: &[f32]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f32::total_cmp);
+
+        // Nearest-rank percentile.
+        let percentile = |p: f32| {
+            let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Self {
+            min: sorted[0],
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+
+    fn to_json(self) -> String {
+        format!(
+            "{{\"min\":{:.4},\"avg\":{:.4},\"p50\":{:.4},\"p95\":{:.4},\"p99\":{:.4},\"max\":{:.4}}}",
+            self.min, self.avg, self.p50, self.p95, self.p99, self.max
+        )
+    }
+}
+
+/// Records the CPU time `update_frame_time` just measured, that of the frame submitted last,
+/// skipping warm-up frames.
+pub fn record_cpu_frame(data: &mut AppData) {
+    let profiling = &mut data.profiling_data;
+    let bench = match &mut profiling.bench {
+        Some(bench) => bench,
+        None => return,
+    };
+
+    if profiling.cpu_timing_updated && bench.rendered > 0 && bench.measured(bench.rendered - 1) {
+        bench.cpu_ms.push(profiling.timings.cpu_frame_ms);
+    }
+}
+
+/// Records the GPU time `update_timings` just read, that of the frame last submitted with this
+/// frame's resources, skipping warm-up frames.
+pub fn record_gpu_frame(data: &mut AppData, frame: usize) {
+    let profiling = &mut data.profiling_data;
+    let bench = match &mut profiling.bench {
+        Some(bench) => bench,
+        None => return,
+    };
+
+    if let Some(index) = bench.submitted[frame].take() {
+        if profiling.gpu_timings_updated && bench.measured(index) {
+            bench.gpu_ms.push(profiling.timings.gpu_ms(GpuPass::Frame));
+        }
+    }
+}
+
+/// Tags this frame's queries with the benchmark frame just submitted.
+pub fn mark_submit(data: &mut AppData, frame: usize) {
+    if let Some(bench) = &mut data.profiling_data.bench {
+        bench.submitted[frame] = Some(bench.rendered);
+        bench.rendered += 1;
+    }
+}
+
+/// Waits for the frames still in flight and records their GPU times, so the last measured
+/// frames aren't left out of the report.
+pub unsafe fn finish(device: &Device, data: &mut AppData) -> Result<()> {
+    let frames = match &data.profiling_data.bench {
+        Some(bench) => bench.submitted.len(),
+        None => return Ok(()),
+    };
+
+    device.device_wait_idle()?;
+
+    for frame in 0..frames {
+        let updated = timestamp::read_pass_timings(device, data, frame)?;
+        let profiling = &mut data.profiling_data;
+        let bench = profiling.bench.as_mut().unwrap();
+
+        if let Some(index) = bench.submitted[frame].take() {
+            if updated && bench.measured(index) {
+                bench.gpu_ms.push(profiling.timings.gpu_ms(GpuPass::Frame));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the benchmark report and writes it as JSON if requested.
+pub fn report(data: &AppData) -> Result<()> {
+    let bench = match &data.profiling_data.bench {
+        Some(bench) => bench,
+        None => return Ok(()),
+    };
+
+    let device = &data.setup_data.device_name;
+    let present_mode = format!("{:?}", data.presentation_data.present_mode);
+    let samples = data.texture_data.msaa_samples.bits();
+    let cpu = FrameTimeStats::new(&bench.cpu_ms);
+    let gpu = FrameTimeStats::new(&bench.gpu_ms);
+
+    println!(
+        "Benchmark: {} frames after {} warm-up frames",
+        bench.cpu_ms.len(),
+        bench.warmup_frames
+    );
+    println!("  Device:       {}", device);
+    println!("  Mode:         windowed (headless is not supported)");
+    println!("  Present mode: {}", present_mode);
+    println!("  MSAA samples: {}", samples);
+    println!("                    min      avg      p50      p95      p99      max");
+    for (name, stats, measured) in [
+        ("CPU frame (ms)", cpu, !bench.cpu_ms.is_empty()),
+        ("GPU frame (ms)", gpu, !bench.gpu_ms.is_empty()),
+    ] {
+        if measured {
+            println!(
+                "  {} {:8.3} {:8.3} {:8.3} {:8.3} {:8.3} {:8.3}",
+                name, stats.min, stats.avg, stats.p50, stats.p95, stats.p99, stats.max
+            );
+        } else {
+            println!("  {}   not measured", name);
+        }
+    }
+
+    if let Some(path) = &bench.json_path {
+        let json = format!(
+            "{{\"device\":\"{}\",\"mode\":\"windowed\",\"present_mode\":\"{}\",\"msaa_samples\":{},\"frames\":{},\"warmup_frames\":{},\"cpu_ms\":{},\"gpu_ms\":{}}}\n",
+            device.replace('\\', "\\\\").replace('"', "\\\""),
+            present_mode,
+            samples,
+            bench.cpu_ms.len(),
+            bench.warmup_frames,
+            cpu.to_json(),
+            if bench.gpu_ms.is_empty() { "null".to_string() } else { gpu.to_json() },
+        );
+
+        fs::write(path, json)?;
+        info!("Wrote benchmark report to `{}`.", path.display());
+    }
+
+    Ok(())
+}
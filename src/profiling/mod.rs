@@ -0,0 +1,67 @@
+use std::time::Instant;
+
+use vulkanalia::prelude::v1_0::*;
+
+use self::bench::Bench;
+use self::timestamp::GpuPass;
+use self::trace::Trace;
+
+pub mod bench;
+pub mod statistics;
+pub mod timestamp;
+pub mod trace;
+
+/// How often averaged timings are logged.
+pub const LOG_INTERVAL_SECS: f32 = 1.0;
+
+/// Timings of one frame, in milliseconds.
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimings {
+    pub cpu_frame_ms: f32,
+    /// GPU time of each pass, indexed like `GpuPass::ALL`.
+    pub gpu_pass_ms: [f32; GpuPass::COUNT],
+}
+
+impl FrameTimings {
+    pub fn gpu_ms(&self, pass: GpuPass) -> f32 {
+        self.gpu_pass_ms[pass as usize]
+    }
+}
+
+/// Pipeline statistics of one frame's draws.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PipelineStatistics {
+    pub vertex_invocations: u64,
+    pub clipping_primitives: u64,
+    pub fragment_invocations: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProfilingData {
+    pub timestamps_supported: bool,
+    /// Nanoseconds per timestamp tick.
+    pub timestamp_period: f32,
+    pub timestamp_mask: u64,
+    pub query_pools: Vec<vk::QueryPool>,
+    /// Empty without `--pipeline-statistics` or if the queries are not supported.
+    pub statistics_query_pools: Vec<vk::QueryPool>,
+    /// Whether each frame has submitted queries yet; until then there is nothing to read.
+    pub queries_pending: Vec<bool>,
+    /// The most recent CPU frame time and GPU pass times of a finished frame.
+    pub timings: FrameTimings,
+    /// Whether `timings` got a new CPU frame time this frame, which needs a previous frame.
+    pub cpu_timing_updated: bool,
+    /// Whether `timings` got new GPU pass times this frame, which needs finished queries.
+    pub gpu_timings_updated: bool,
+    /// The pipeline statistics of the most recent finished frame.
+    pub statistics: PipelineStatistics,
+    pub last_frame: Option<Instant>,
+    /// Set when benchmarking with `--bench`.
+    pub bench: Option<Bench>,
+    /// Set when tracing with `--trace`.
+    pub trace: Option<Trace>,
+    pub last_log: Option<Instant>,
+    pub last_statistics_log: Option<Instant>,
+    pub accumulated: FrameTimings,
+    pub accumulated_frames: u32,
+}
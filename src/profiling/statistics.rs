@@ -0,0 +1,183 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::profiling::{PipelineStatistics, ProfilingData, LOG_INTERVAL_SECS};
+use crate::AppData;
+
+/// The order results are written in follows the bit order of these flags.
+const STATISTICS: vk::QueryPipelineStatisticFlags =
+    vk::QueryPipelineStatisticFlags::from_bits_truncate(
+        vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.bits()
+            | vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.bits()
+            | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.bits(),
+    );
+const STATISTIC_COUNT: usize = 3;
+
+/// Creates one query per recording thread and frame, since queries can't stay active across
+/// secondary command buffers without `inherited_queries`.
+///
+/// Without `--pipeline-statistics` no pools are created, which leaves the queries out of the
+/// recorded command buffers.
+pub unsafe fn create_statistics_query_pools(device: &Device, data: &mut AppData) -> Result<()> {
+    if !data.config.pipeline_statistics {
+        return Ok(());
+    }
+
+    if data.setup_data.features.pipeline_statistics_query != vk::TRUE {
+        warn!("Pipeline statistics queries are not supported.");
+        return Ok(());
+    }
+
+    data.profiling_data.statistics_query_pools = data
+        .drawing_data
+        .frames
+        .iter()
+        .map(|f| {
+            let info = vk::QueryPoolCreateInfo::builder()
+                .query_type(vk::QueryType::PIPELINE_STATISTICS)
+                .query_count(f.secondary_command_buffers.len() as u32)
+                .pipeline_statistics(STATISTICS);
+
+            device.create_query_pool(&info, None)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Pipeline statistics query pools created.");
+    Ok(())
+}
+
+pub fn statistics_enabled(data: &AppData) -> bool {
+    !data.profiling_data.statistics_query_pools.is_empty()
+}
+
+/// Resets this frame's queries; must be recorded outside a render pass.
+pub unsafe fn reset_statistics_queries(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+) {
+    if statistics_enabled(data) {
+        device.cmd_reset_query_pool(
+            command_buffer,
+            data.profiling_data.statistics_query_pools[frame],
+            0,
+            data.drawing_data.frames[frame]
+                .secondary_command_buffers
+                .len() as u32,
+        );
+    }
+}
+
+pub unsafe fn begin_statistics_query(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    worker: usize,
+) {
+    if statistics_enabled(data) {
+        device.cmd_begin_query(
+            command_buffer,
+            data.profiling_data.statistics_query_pools[frame],
+            worker as u32,
+            vk::QueryControlFlags::empty(),
+        );
+    }
+}
+
+pub unsafe fn end_statistics_query(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    worker: usize,
+) {
+    if statistics_enabled(data) {
+        device.cmd_end_query(
+            command_buffer,
+            data.profiling_data.statistics_query_pools[frame],
+            worker as u32,
+        );
+    }
+}
+
+/// Sums the statistics of the recording threads of this frame's previous submission.
+///
+/// Called after the frame's fence has been waited on. Threads that recorded nothing never
+/// began their query, which leaves it unavailable, so those are skipped.
+pub unsafe fn update_statistics(device: &Device, data: &mut AppData, frame: usize) -> Result<()> {
+    if !statistics_enabled(data) || !data.profiling_data.queries_pending[frame] {
+        return Ok(());
+    }
+
+    let query_count = data.drawing_data.frames[frame]
+        .secondary_command_buffers
+        .len();
+    let stride = STATISTIC_COUNT + 1;
+
+    let mut results = vec![0u64; query_count * stride];
+    device.get_query_pool_results(
+        data.profiling_data.statistics_query_pools[frame],
+        0,
+        query_count as u32,
+        std::slice::from_raw_parts_mut(
+            results.as_mut_ptr().cast::<u8>(),
+            results.len() * std::mem::size_of::<u64>(),
+        ),
+        (stride * std::mem::size_of::<u64>()) as u64,
+        vk::QueryResultFlags::_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+    )?;
+
+    let mut statistics = PipelineStatistics::default();
+    for result in results
+        .chunks_exact(stride)
+        .filter(|r| r[STATISTIC_COUNT] != 0)
+    {
+        statistics.vertex_invocations += result[0];
+        statistics.clipping_primitives += result[1];
+        statistics.fragment_invocations += result[2];
+    }
+
+    data.profiling_data.statistics = statistics;
+    log_statistics(&mut data.profiling_data);
+
+    Ok(())
+}
+
+/// Logs the statistics of the latest finished frame every `LOG_INTERVAL_SECS`.
+fn log_statistics(profiling: &mut ProfilingData) {
+    let now = Instant::now();
+    let last_log = *profiling.last_statistics_log.get_or_insert(now);
+    if (now - last_log).as_secs_f32() < LOG_INTERVAL_SECS {
+        return;
+    }
+
+    let statistics = profiling.statistics;
+    info!(
+        "Last frame: {} vertex invocations, {} clipping primitives, {} fragment invocations.",
+        statistics.vertex_invocations,
+        statistics.clipping_primitives,
+        statistics.fragment_invocations
+    );
+
+    profiling.last_statistics_log = Some(now);
+}
+
+/// Destroys the query pool of a frame nothing renders anymore.
+pub unsafe fn release_statistics_query_pool(device: &Device, data: &mut AppData, frame: usize) {
+    if let Some(pool) = data.profiling_data.statistics_query_pools.get_mut(frame) {
+        device.destroy_query_pool(*pool, None);
+        *pool = vk::QueryPool::null();
+    }
+}
+
+pub unsafe fn destroy_statistics_query_pools(device: &Device, data: &AppData) {
+    data.profiling_data
+        .statistics_query_pools
+        .iter()
+        .for_each(|p| device.destroy_query_pool(*p, None));
+}
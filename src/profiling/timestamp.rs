@@ -0,0 +1,268 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::profiling::{FrameTimings, LOG_INTERVAL_SECS};
+use crate::setup::device::queue_families;
+use crate::AppData;
+
+/// A section of the command buffer bracketed by a pair of timestamps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GpuPass {
+    /// The whole command buffer.
+    Frame,
+    Culling,
+    MainPass,
+}
+
+impl GpuPass {
+    pub const COUNT: usize = 3;
+    pub const ALL: [GpuPass; Self::COUNT] = [GpuPass::Frame, GpuPass::Culling, GpuPass::MainPass];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GpuPass::Frame => "frame",
+            GpuPass::Culling => "culling",
+            GpuPass::MainPass => "main pass",
+        }
+    }
+
+    fn begin_query(self) -> u32 {
+        self as u32 * 2
+    }
+
+    fn end_query(self) -> u32 {
+        self as u32 * 2 + 1
+    }
+}
+
+const QUERY_COUNT: u32 = GpuPass::COUNT as u32 * 2;
+
+pub unsafe fn create_timestamp_query_pools(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let physical_device = data.setup_data.physical_device;
+    let indices = queue_families::QueueFamilyIndices::get(instance, data, physical_device)?;
+
+    let valid_bits = instance.get_physical_device_queue_family_properties(physical_device)
+        [indices.graphics as usize]
+        .timestamp_valid_bits;
+
+    let properties = instance.get_physical_device_properties(physical_device);
+
+    data.profiling_data.timestamps_supported = valid_bits > 0;
+    data.profiling_data.timestamp_period = properties.limits.timestamp_period;
+    data.profiling_data.timestamp_mask = if valid_bits >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << valid_bits) - 1
+    };
+
+    data.profiling_data.queries_pending = vec![false; data.drawing_data.frames.len()];
+
+    if !data.profiling_data.timestamps_supported {
+        warn!("Timestamp queries are not supported on the graphics queue.");
+        return Ok(());
+    }
+
+    let info = vk::QueryPoolCreateInfo::builder()
+        .query_type(vk::QueryType::TIMESTAMP)
+        .query_count(QUERY_COUNT);
+
+    data.profiling_data.query_pools = (0..data.drawing_data.frames.len())
+        .map(|_| device.create_query_pool(&info, None))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Timestamp query pools created.");
+    Ok(())
+}
+
+/// Resets this frame's queries; must be recorded outside a render pass before any `begin_pass`.
+pub unsafe fn reset_queries(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+) {
+    if data.profiling_data.timestamps_supported {
+        let query_pool = data.profiling_data.query_pools[frame];
+        device.cmd_reset_query_pool(command_buffer, query_pool, 0, QUERY_COUNT);
+    }
+}
+
+pub unsafe fn begin_pass(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    pass: GpuPass,
+) {
+    if data.profiling_data.timestamps_supported {
+        device.cmd_write_timestamp(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            data.profiling_data.query_pools[frame],
+            pass.begin_query(),
+        );
+    }
+}
+
+pub unsafe fn end_pass(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    frame: usize,
+    pass: GpuPass,
+) {
+    if data.profiling_data.timestamps_supported {
+        device.cmd_write_timestamp(
+            command_buffer,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            data.profiling_data.query_pools[frame],
+            pass.end_query(),
+        );
+    }
+}
+
+/// Reads back the GPU pass times of this frame's previous submission into `timings`.
+///
+/// Returns whether there were results to read; they are only available once the frame's fence
+/// has signaled.
+pub unsafe fn read_pass_timings(device: &Device, data: &mut AppData, frame: usize) -> Result<bool> {
+    let profiling = &mut data.profiling_data;
+    if !profiling.timestamps_supported || !profiling.queries_pending[frame] {
+        return Ok(false);
+    }
+
+    let mut results = [0u64; QUERY_COUNT as usize];
+
+    let result = device.get_query_pool_results(
+        profiling.query_pools[frame],
+        0,
+        QUERY_COUNT,
+        std::slice::from_raw_parts_mut(
+            results.as_mut_ptr().cast::<u8>(),
+            std::mem::size_of_val(&results),
+        ),
+        std::mem::size_of::<u64>() as u64,
+        vk::QueryResultFlags::_64,
+    )?;
+
+    if result != vk::SuccessCode::SUCCESS {
+        return Ok(false);
+    }
+
+    let mask = profiling.timestamp_mask;
+    let period = profiling.timestamp_period as f64;
+    let frame_begin = results[GpuPass::Frame.begin_query() as usize] & mask;
+
+    for pass in GpuPass::ALL {
+        let begin = results[pass.begin_query() as usize] & mask;
+        let end = results[pass.end_query() as usize] & mask;
+        let ticks = end.wrapping_sub(begin) & mask;
+
+        profiling.timings.gpu_pass_ms[pass as usize] = (ticks as f64 * period / 1e6) as f32;
+
+        if let Some(trace) = &mut profiling.trace {
+            let offset = begin.wrapping_sub(frame_begin) & mask;
+            trace.gpu_range(
+                frame,
+                pass.name(),
+                offset as f64 * period / 1e3,
+                ticks as f64 * period / 1e3,
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+/// Reads back the GPU pass times of this frame's previous submission.
+///
+/// Called after the frame's fence has been waited on, so results are already available and
+/// this never stalls.
+pub unsafe fn update_timings(device: &Device, data: &mut AppData, frame: usize) -> Result<()> {
+    data.profiling_data.gpu_timings_updated = read_pass_timings(device, data, frame)?;
+    Ok(())
+}
+
+/// Records the CPU frame time and periodically logs averages.
+///
+/// Called once per frame before any window renders, so the frame time covers every window.
+pub fn update_frame_time(data: &mut AppData) {
+    let now = Instant::now();
+    let profiling = &mut data.profiling_data;
+
+    profiling.cpu_timing_updated = profiling.last_frame.is_some();
+    if let Some(last_frame) = profiling.last_frame {
+        profiling.timings.cpu_frame_ms = (now - last_frame).as_secs_f32() * 1000.0;
+    }
+    profiling.last_frame = Some(now);
+
+    profiling.accumulated.cpu_frame_ms += profiling.timings.cpu_frame_ms;
+    for pass in GpuPass::ALL {
+        profiling.accumulated.gpu_pass_ms[pass as usize] += profiling.timings.gpu_ms(pass);
+    }
+    profiling.accumulated_frames += 1;
+
+    let last_log = *profiling.last_log.get_or_insert(now);
+    if (now - last_log).as_secs_f32() >= LOG_INTERVAL_SECS {
+        log_timings(
+            &profiling.accumulated,
+            profiling.accumulated_frames,
+            profiling.timestamps_supported,
+        );
+
+        profiling.accumulated = FrameTimings::default();
+        profiling.accumulated_frames = 0;
+        profiling.last_log = Some(now);
+    }
+}
+
+fn log_timings(accumulated: &FrameTimings, frames: u32, gpu_supported: bool) {
+    let average = |ms: f32| ms / frames as f32;
+
+    if !gpu_supported {
+        info!(
+            "CPU frame {:.3} ms (over {} frames).",
+            average(accumulated.cpu_frame_ms),
+            frames
+        );
+        return;
+    }
+
+    let gpu = GpuPass::ALL
+        .iter()
+        .map(|p| format!("{} {:.3} ms", p.name(), average(accumulated.gpu_ms(*p))))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    info!(
+        "CPU frame {:.3} ms, GPU {} (over {} frames).",
+        average(accumulated.cpu_frame_ms),
+        gpu,
+        frames
+    );
+}
+
+/// Destroys the query pool of a frame nothing renders anymore.
+pub unsafe fn release_timestamp_query_pool(device: &Device, data: &mut AppData, frame: usize) {
+    let profiling = &mut data.profiling_data;
+    profiling.queries_pending[frame] = false;
+
+    if let Some(pool) = profiling.query_pools.get_mut(frame) {
+        device.destroy_query_pool(*pool, None);
+        *pool = vk::QueryPool::null();
+    }
+}
+
+pub unsafe fn destroy_timestamp_query_pools(device: &Device, data: &AppData) {
+    data.profiling_data
+        .query_pools
+        .iter()
+        .for_each(|p| device.destroy_query_pool(*p, None));
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::Result;
+use log::*;
+
+use crate::AppData;
+
+/// Stop recording past this many events, so a long session can't exhaust memory.
+const MAX_TRACE_EVENTS: usize = 1_000_000;
+
+const CPU_THREAD: u32 = 1;
+const GPU_THREAD: u32 = 2;
+
+/// A complete (`"ph": "X"`) event of the Chrome Trace Event format.
+#[derive(Copy, Clone, Debug)]
+pub struct TraceEvent {
+    pub name: &'static str,
+    pub thread: u32,
+    /// Microseconds since the trace started.
+    pub start_us: f64,
+    pub duration_us: f64,
+}
+
+/// CPU spans and GPU ranges collected while tracing, written out on exit.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub path: PathBuf,
+    pub start: Instant,
+    pub events: Vec<TraceEvent>,
+    /// Set once `MAX_TRACE_EVENTS` is reached and events start being dropped.
+    pub truncated: bool,
+    /// When each frame in flight was last submitted, which GPU ranges are placed after.
+    pub submit_us: Vec<f64>,
+}
+
+impl Trace {
+    pub fn new(path: PathBuf, frames_in_flight: usize) -> Self {
+        Self {
+            path,
+            start: Instant::now(),
+            events: vec![],
+            truncated: false,
+            submit_us: vec![0.0; frames_in_flight],
+        }
+    }
+
+    fn micros(&self, instant: Instant) -> f64 {
+        instant.saturating_duration_since(self.start).as_secs_f64() * 1_000_000.0
+    }
+
+    /// Records a GPU range given in microseconds after the frame's first timestamp.
+    ///
+    /// Without calibrated timestamps the GPU clock can't be mapped onto the CPU clock, so the
+    /// frame's first timestamp is placed at its submission.
+    pub fn gpu_range(
+        &mut self,
+        frame: usize,
+        name: &'static str,
+        offset_us: f64,
+        duration_us: f64,
+    ) {
+        self.push(TraceEvent {
+            name,
+            thread: GPU_THREAD,
+            start_us: self.submit_us[frame] + offset_us,
+            duration_us,
+        });
+    }
+
+    fn push(&mut self, event: TraceEvent) {
+        if self.events.len() < MAX_TRACE_EVENTS {
+            self.events.push(event);
+        } else if !self.truncated {
+            warn!("Trace is full, further events are dropped.");
+            self.truncated = true;
+        }
+    }
+}
+
+/// Records a CPU span from `start` until now, if tracing.
+pub fn span(data: &mut AppData, name: &'static str, start: Instant) {
+    if let Some(trace) = &mut data.profiling_data.trace {
+        let start_us = trace.micros(start);
+        let end_us = trace.micros(Instant::now());
+        trace.push(TraceEvent {
+            name,
+            thread: CPU_THREAD,
+            start_us,
+            duration_us: end_us - start_us,
+        });
+    }
+}
+
+/// Remembers when a frame was submitted, so its GPU ranges can be placed on the timeline.
+pub fn mark_submit(data: &mut AppData, frame: usize) {
+    if let Some(trace) = &mut data.profiling_data.trace {
+        trace.submit_us[frame] = trace.micros(Instant::now());
+    }
+}
+
+/// Writes the trace as Chrome Trace Event JSON, loadable in `chrome://tracing` or Perfetto.
+pub fn write_trace(data: &AppData) -> Result<()> {
+    let trace = match &data.profiling_data.trace {
+        Some(trace) => trace,
+        None => return Ok(()),
+    };
+
+    let mut lines = vec![];
+
+    for (thread, name) in [(CPU_THREAD, "CPU"), (GPU_THREAD, "GPU")] {
+        lines.push(format!(
+            "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+            thread, name
+        ));
+    }
+
+    for event in &trace.events {
+        lines.push(format!(
+            "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{:.3},\"dur\":{:.3}}}",
+            event.name,
+            if event.thread == GPU_THREAD { "gpu" } else { "cpu" },
+            event.thread,
+            event.start_us,
+            event.duration_us
+        ));
+    }
+
+    let json = format!(
+        "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}\n",
+        lines.join(",\n")
+    );
+
+    fs::write(&trace.path, json)?;
+
+    info!(
+        "Wrote {} trace events to `{}`.",
+        trace.events.len(),
+        trace.path.display()
+    );
+    Ok(())
+}
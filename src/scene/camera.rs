@@ -0,0 +1,48 @@
+use cgmath::{point3, vec3, Deg};
+
+type Point3 = cgmath::Point3<f32>;
+type Vec3 = cgmath::Vector3<f32>;
+type Mat4 = cgmath::Matrix4<f32>;
+
+/// Where a window looks at the scene from.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub eye: Point3,
+    pub target: Point3,
+    pub up: Vec3,
+    /// Vertical field of view.
+    pub fov: Deg<f32>,
+    /// Height of the view volume for an orthographic projection, which ignores `fov`.
+    pub orthographic: Option<f32>,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            eye: point3(2.0, 2.0, 2.0),
+            target: point3(0.0, 0.0, 0.0),
+            up: vec3(0.0, 0.0, 1.0),
+            fov: Deg(45.0),
+            orthographic: None,
+            near: 0.1,
+            far: 10.0,
+        }
+    }
+}
+
+impl Camera {
+    /// A closer view of a single object at the origin, for the material preview.
+    pub fn preview() -> Self {
+        Self {
+            eye: point3(1.2, 1.2, 0.9),
+            target: point3(0.0, 0.0, 0.2),
+            ..Self::default()
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+}
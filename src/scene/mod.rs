@@ -0,0 +1,45 @@
+use self::object::SceneObject;
+
+pub mod camera;
+pub mod object;
+
+/// A stable handle to a scene object, valid until the object is removed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId(pub(crate) u64);
+
+#[derive(Clone, Debug, Default)]
+pub struct SceneData {
+    pub objects: Vec<SceneObject>,
+    next_id: u64,
+}
+
+impl SceneData {
+    pub fn add(&mut self, mut object: SceneObject) -> ObjectId {
+        let id = ObjectId(self.next_id);
+        self.next_id += 1;
+
+        object.id = id;
+        self.objects.push(object);
+
+        id
+    }
+
+    pub fn remove(&mut self, id: ObjectId) -> Option<SceneObject> {
+        let index = self.objects.iter().position(|o| o.id == id)?;
+        Some(self.objects.remove(index))
+    }
+
+    pub fn get_mut(&mut self, id: ObjectId) -> Option<&mut SceneObject> {
+        self.objects.iter_mut().find(|o| o.id == id)
+    }
+
+    pub fn set_visible(&mut self, id: ObjectId, visible: bool) -> bool {
+        match self.get_mut(id) {
+            Some(object) => {
+                object.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+}
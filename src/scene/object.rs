@@ -0,0 +1,95 @@
+use cgmath::{vec3, Deg, SquareMatrix};
+
+use crate::presentation::window::WindowRole;
+use crate::profiling::bench;
+use crate::scene::{ObjectId, SceneData};
+use crate::vertex::instance::InstanceData;
+use crate::AppData;
+
+type Mat4 = cgmath::Matrix4<f32>;
+
+/// A placed copy of the loaded model, drawn with its own model matrix.
+///
+/// Every object is drawn once per entry in `instances`, relative to its own model matrix.
+#[derive(Clone, Debug)]
+pub struct SceneObject {
+    /// Assigned by `SceneData::add`.
+    pub id: ObjectId,
+    /// Hidden objects stay in the scene but are left out of the draw list.
+    pub visible: bool,
+    pub transform: Mat4,
+    /// Rotation around the Z axis per second.
+    pub spin: Deg<f32>,
+    pub material_index: u32,
+    pub instances: Vec<InstanceData>,
+    /// Frames since an occlusion query found this object hidden, or 0 if it wasn't.
+    pub occluded_frames: u32,
+}
+
+impl SceneObject {
+    pub fn new(transform: Mat4) -> Self {
+        Self {
+            id: ObjectId(u64::MAX),
+            visible: true,
+            transform,
+            spin: Deg(0.0),
+            material_index: 0,
+            instances: vec![InstanceData::default()],
+            occluded_frames: 0,
+        }
+    }
+
+    pub fn model(&self, time: f32) -> Mat4 {
+        self.transform * Mat4::from_axis_angle(vec3(0.0, 0.0, 1.0), self.spin * time)
+    }
+}
+
+/// Places the `index`-th object on a grid, eight objects per row.
+pub fn grid_transform(index: usize) -> Mat4 {
+    let x = (index % 8) as f32 - 3.5;
+    let y = (index / 8) as f32;
+    Mat4::from_translation(vec3(x * 2.5, y * 2.5, 0.0))
+}
+
+pub fn create_scene(data: &mut AppData) {
+    if data.profiling_data.bench.is_some() {
+        create_bench_scene(data);
+    } else {
+        data.scene_data.add(SceneObject {
+            spin: Deg(90.0),
+            ..SceneObject::new(Mat4::identity())
+        });
+    }
+
+    for window in &mut data.windows {
+        if window.role == WindowRole::MaterialPreview {
+            window.scene = Some(create_preview_scene(&data.scene_data));
+        }
+    }
+}
+
+/// A single spinning object with the first scene object's material, for the material preview.
+///
+/// The object keeps an unassigned id, so occlusion results of the preview never affect the scene.
+pub fn create_preview_scene(scene: &SceneData) -> SceneData {
+    let material_index = scene.objects.first().map_or(0, |o| o.material_index);
+
+    SceneData {
+        objects: vec![SceneObject {
+            spin: Deg(45.0),
+            material_index,
+            ..SceneObject::new(Mat4::identity())
+        }],
+        ..Default::default()
+    }
+}
+
+/// A fixed grid of spinning objects, so benchmark runs are comparable.
+fn create_bench_scene(data: &mut AppData) {
+    for i in 0..bench::BENCH_OBJECTS {
+        data.scene_data.add(SceneObject {
+            spin: Deg(90.0),
+            ..SceneObject::new(grid_transform(i))
+        });
+    }
+}
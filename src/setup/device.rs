@@ -7,6 +7,7 @@ use vulkanalia::prelude::v1_0::*;
 use vulkanalia::Instance;
 
 use crate::presentation::swapchain::{SwapchainSupport, DEVICE_EXTENSIONS};
+use crate::presentation::window;
 use crate::texture::multisampling;
 use crate::{AppData, VALIDATION_ENABLED, VALIDATION_LAYER};
 
@@ -28,6 +29,7 @@ pub unsafe fn pick_physical_device(instance: &Instance, data: &mut AppData) -> R
         } else {
             info!("Selected physical device (`{}`).", properties.device_name);
             data.setup_data.physical_device = physical_device;
+            data.setup_data.device_name = properties.device_name.to_string();
 
             data.texture_data.msaa_samples = multisampling::get_max_msaa_samples(instance, data);
 
@@ -46,15 +48,11 @@ unsafe fn check_physical_device(
     queue_families::QueueFamilyIndices::get(instance, data, physical_device)?;
     check_physical_device_extensions(instance, physical_device)?;
 
-    let features = instance.get_physical_device_features(physical_device);
-
-    if features.multi_viewport != vk::TRUE {
-        return Err(anyhow!(SuitabilityError("Missing multi viewport support.")));
-    }
-
-    let support = SwapchainSupport::get(instance, data, physical_device)?;
-    if support.formats.is_empty() || support.present_modes.is_empty() {
-        return Err(anyhow!(SuitabilityError("Insufficient swapchain support.")));
+    for surface in window::get_surfaces(data) {
+        let support = SwapchainSupport::get(instance, physical_device, surface)?;
+        if support.formats.is_empty() || support.present_modes.is_empty() {
+            return Err(anyhow!(SuitabilityError("Insufficient swapchain support.")));
+        }
     }
 
     let features = instance.get_physical_device_features(physical_device);
@@ -117,9 +115,18 @@ pub unsafe fn create_logical_device(
         .map(|n| n.as_ptr())
         .collect::<Vec<_>>();
 
+    let supported = instance.get_physical_device_features(data.setup_data.physical_device);
+
     let features = vk::PhysicalDeviceFeatures::builder()
         .sampler_anisotropy(true)
-        .sample_rate_shading(true); // Enable sample shading feature for the device.
+        .sample_rate_shading(true) // Enable sample shading feature for the device.
+        .fill_mode_non_solid(supported.fill_mode_non_solid == vk::TRUE) // Wireframe view mode.
+        .pipeline_statistics_query(
+            data.config.pipeline_statistics && supported.pipeline_statistics_query == vk::TRUE,
+        )
+        .occlusion_query_precise(supported.occlusion_query_precise == vk::TRUE);
+
+    data.setup_data.features = features.build();
 
     let info = vk::DeviceCreateInfo::builder()
         .queue_create_infos(&queue_infos)
@@ -3,6 +3,7 @@ use vulkanalia::prelude::v1_0::*;
 use vulkanalia::vk::KhrSurfaceExtension;
 use vulkanalia::Instance;
 
+use crate::presentation::window;
 use crate::setup::device::SuitabilityError;
 use crate::AppData;
 
@@ -26,21 +27,31 @@ impl QueueFamilyIndices {
             .position(|p| p.queue_flags.contains(vk::QueueFlags::TRANSFER))
             .map(|i| i as u32);
 
+        // Culling runs in compute on the graphics queue.
         let graphics = properties
             .iter()
-            .position(|p| p.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+            .position(|p| {
+                p.queue_flags
+                    .contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+            })
             .map(|i| i as u32);
 
+        // Every window presents from the same queue, so it must support all of their surfaces.
+        let surfaces = window::get_surfaces(data);
         let mut present = None;
-        for (index, properties) in properties.iter().enumerate() {
-            if instance.get_physical_device_surface_support_khr(
-                physical_device,
-                index as u32,
-                data.surface,
-            )? {
-                present = Some(index as u32);
-                break;
+        'families: for (index, properties) in properties.iter().enumerate() {
+            for surface in &surfaces {
+                if !instance.get_physical_device_surface_support_khr(
+                    physical_device,
+                    index as u32,
+                    *surface,
+                )? {
+                    continue 'families;
+                }
             }
+
+            present = Some(index as u32);
+            break;
         }
 
         if let (Some(transfer), Some(graphics), Some(present)) = (transfer, graphics, present) {
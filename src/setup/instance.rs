@@ -46,6 +46,19 @@ pub unsafe fn create_instance(
         extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
     }
 
+    let available_extensions = entry
+        .enumerate_instance_extension_properties(None)?
+        .iter()
+        .map(|e| e.extension_name)
+        .collect::<HashSet<_>>();
+
+    data.setup_data.swapchain_colorspace =
+        available_extensions.contains(&vk::EXT_SWAPCHAIN_COLORSPACE_EXTENSION.name);
+
+    if data.setup_data.swapchain_colorspace {
+        extensions.push(vk::EXT_SWAPCHAIN_COLORSPACE_EXTENSION.name.as_ptr());
+    }
+
     let flags = vk::InstanceCreateFlags::empty();
 
     let mut info = vk::InstanceCreateInfo::builder()
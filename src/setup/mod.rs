@@ -7,7 +7,11 @@ pub mod instance;
 #[derive(Clone, Debug, Default)]
 pub struct SetupData {
     pub messenger: DebugUtilsMessengerEXT,
+    /// Whether `VK_EXT_swapchain_colorspace` is enabled, for HDR color spaces.
+    pub swapchain_colorspace: bool,
     pub physical_device: vk::PhysicalDevice,
+    pub device_name: String,
+    pub features: vk::PhysicalDeviceFeatures,
     pub transfer_queue: vk::Queue,
     pub graphics_queue: vk::Queue,
     pub present_queue: vk::Queue,
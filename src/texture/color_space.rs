@@ -0,0 +1,47 @@
+use vulkanalia::prelude::v1_0::*;
+
+use crate::texture::container::FORMAT_PAIRS;
+
+/// How a texture's texels are interpreted, which picks between `_SRGB` and `_UNORM` formats.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Colors authored on a display, decoded to linear when sampled.
+    #[default]
+    Srgb,
+    /// Data sampled as is, like normals, roughness and metalness, or lookup tables.
+    Linear,
+}
+
+impl ColorSpace {
+    pub fn is_srgb(self) -> bool {
+        self == Self::Srgb
+    }
+
+    /// The color space of a format with both a `_UNORM` and an `_SRGB` variant.
+    pub fn of(format: vk::Format) -> Option<Self> {
+        FORMAT_PAIRS.iter().find_map(|(unorm, srgb)| {
+            if *srgb == format {
+                Some(Self::Srgb)
+            } else if *unorm == format {
+                Some(Self::Linear)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The variant of `format` in this color space, or `format` if it only has one.
+    pub fn apply(self, format: vk::Format) -> vk::Format {
+        let Some((unorm, srgb)) = FORMAT_PAIRS
+            .iter()
+            .find(|(unorm, srgb)| *unorm == format || *srgb == format)
+        else {
+            return format;
+        };
+
+        match self {
+            Self::Srgb => *srgb,
+            Self::Linear => *unorm,
+        }
+    }
+}
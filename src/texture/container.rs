@@ -0,0 +1,578 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use ddsfile::{D3DFormat, Dds, DxgiFormat};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::texture::color_space::ColorSpace;
+use crate::texture::decode::{self, SourceFormat};
+
+/// A texture read from a KTX2 or DDS file, with every mip level and array layer.
+#[derive(Clone, Debug)]
+pub struct TextureFile {
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    /// Array layers, counting each face of a cubemap as a layer.
+    pub array_layers: u32,
+    pub cube: bool,
+    /// Whether the file doesn't say if its texels are sRGB, as with legacy DDS formats.
+    pub ambiguous_color_space: bool,
+    /// The data of each mip level, largest first, with its layers one after the other.
+    pub levels: Vec<Vec<u8>>,
+}
+
+/// Whether `path` is a KTX2 or DDS file.
+pub fn is_texture_file(path: &Path) -> bool {
+    matches!(
+        decode::detect_file_format(path),
+        Ok(SourceFormat::Ktx2 | SourceFormat::Dds)
+    )
+}
+
+impl TextureFile {
+    /// Reads a KTX2 or DDS file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = match decode::detect_file_format(path)? {
+            SourceFormat::Ktx2 => load_ktx2(path)?,
+            SourceFormat::Dds => load_dds(path)?,
+            _ => return Err(anyhow!("`{}` is not a KTX2 or DDS file.", path.display())),
+        };
+
+        file.validate(path)?;
+        Ok(file)
+    }
+
+    /// Checks the dimensions and that every mip level holds exactly its layers.
+    fn validate(&self, path: &Path) -> Result<()> {
+        let max_levels = self.width.max(self.height).max(1).ilog2() + 1;
+        if self.width == 0 || self.height == 0 || self.array_layers == 0 {
+            return Err(anyhow!("Texture file `{}` is empty.", path.display()));
+        } else if self.mip_levels == 0 || self.mip_levels > max_levels {
+            return Err(anyhow!(
+                "Texture file `{}` has {} mip levels, expected 1 to {}.",
+                path.display(),
+                self.mip_levels,
+                max_levels
+            ));
+        } else if self.levels.len() != self.mip_levels as usize {
+            return Err(anyhow!(
+                "Texture file `{}` is missing mip levels.",
+                path.display()
+            ));
+        }
+
+        let block = get_block_info(self.format).unwrap();
+        for (level, data) in self.levels.iter().enumerate() {
+            let (width, height) = self.level_extent(level as u32);
+            let size = block.level_size(width, height) * self.array_layers as usize;
+            if data.len() != size {
+                return Err(anyhow!(
+                    "Mip level {} of texture file `{}` is {} bytes, expected {}.",
+                    level,
+                    path.display(),
+                    data.len(),
+                    size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The size of a mip level, in texels.
+    pub fn level_extent(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// Whether the texture uses a block-compressed format.
+    pub fn is_compressed(&self) -> bool {
+        get_block_info(self.format).is_some_and(|b| b.width > 1)
+    }
+
+    /// Decodes a block-compressed texture into 8-bit BGRA, for devices that can't sample it.
+    pub fn decompress(&self) -> Result<Self> {
+        let decoder = get_decoder(self.format)
+            .ok_or_else(|| anyhow!("No CPU decoder for {:?}.", self.format))?;
+        let block = get_block_info(self.format).unwrap();
+
+        let levels = self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let (width, height) = self.level_extent(level as u32);
+                let layer_size = block.level_size(width, height);
+
+                let mut pixels =
+                    Vec::with_capacity((width * height * 4 * self.array_layers) as usize);
+                // Levels are checked to hold exactly `array_layers` layers on load.
+                for layer in data.chunks_exact(layer_size) {
+                    let mut image = vec![0u32; (width * height) as usize];
+                    decoder(layer, width as usize, height as usize, &mut image)
+                        .map_err(|e| anyhow!("Failed to decode {:?}: {}", self.format, e))?;
+                    // The decoders write each texel as a little-endian BGRA `u32`.
+                    pixels.extend(image.iter().flat_map(|p| p.to_le_bytes()));
+                }
+
+                Ok(pixels)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let color_space = ColorSpace::of(self.format).unwrap_or(ColorSpace::Linear);
+        let format = color_space.apply(vk::Format::B8G8R8A8_UNORM);
+
+        Ok(Self {
+            format,
+            levels,
+            ..self.clone()
+        })
+    }
+}
+
+fn load_ktx2(path: &Path) -> Result<TextureFile> {
+    let bytes = fs::read(path)?;
+    let reader = ktx2::Reader::new(&bytes)
+        .map_err(|e| anyhow!("Invalid KTX2 file `{}`: {}", path.display(), e))?;
+    let header = reader.header();
+
+    if let Some(scheme) = header.supercompression_scheme {
+        return Err(anyhow!(
+            "KTX2 supercompression ({:?}) is not supported.",
+            scheme
+        ));
+    }
+
+    let format = header
+        .format
+        .map(|f| vk::Format::from_raw(f.value() as i32))
+        .ok_or_else(|| anyhow!("KTX2 file `{}` has no Vulkan format.", path.display()))?;
+
+    if get_block_info(format).is_none() {
+        return Err(anyhow!("KTX2 format {:?} is not supported.", format));
+    }
+
+    if header.pixel_depth > 1 {
+        return Err(anyhow!("3D textures are not supported."));
+    }
+
+    if header.pixel_width == 0 || !matches!(header.face_count, 1 | 6) {
+        return Err(anyhow!(
+            "KTX2 file `{}` has an invalid size ({}x{}, {} faces).",
+            path.display(),
+            header.pixel_width,
+            header.pixel_height,
+            header.face_count
+        ));
+    }
+
+    let levels = reader.levels().map(|l| l.data.to_vec()).collect::<Vec<_>>();
+
+    Ok(TextureFile {
+        format,
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        mip_levels: levels.len() as u32,
+        array_layers: header.layer_count.max(1) * header.face_count,
+        cube: header.face_count == 6,
+        ambiguous_color_space: false,
+        levels,
+    })
+}
+
+fn load_dds(path: &Path) -> Result<TextureFile> {
+    let dds = Dds::read(fs::File::open(path)?)?;
+
+    // Legacy D3D formats have no `_SRGB` variants, so they don't say what their texels hold.
+    let (format, ambiguous_color_space) = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+        (Some(format), _) => get_dxgi_format(format).map(|f| (f, false)),
+        (None, Some(format)) => get_d3d_format(format).map(|f| (f, true)),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("DDS file `{}` has an unsupported format.", path.display()))?;
+
+    let block = get_block_info(format).unwrap();
+    let (width, height) = (dds.get_width(), dds.get_height());
+    let mip_levels = dds.get_num_mipmap_levels();
+    let array_layers = dds.get_num_array_layers();
+
+    // DDS stores each layer with all of its mips, where Vulkan copies want each mip with all
+    // of its layers.
+    let mut levels = vec![vec![]; mip_levels as usize];
+    for layer in 0..array_layers {
+        let mut data = dds.get_data(layer)?;
+        for (level, level_data) in levels.iter_mut().enumerate() {
+            let size = block.level_size((width >> level).max(1), (height >> level).max(1));
+            if data.len() < size {
+                return Err(anyhow!("DDS file `{}` is cut short.", path.display()));
+            }
+
+            level_data.extend_from_slice(&data[..size]);
+            data = &data[size..];
+        }
+    }
+
+    Ok(TextureFile {
+        format,
+        width,
+        height,
+        mip_levels,
+        array_layers,
+        cube: array_layers % 6 == 0 && dds.header.caps2.contains(ddsfile::Caps2::CUBEMAP),
+        ambiguous_color_space,
+        levels,
+    })
+}
+
+fn get_dxgi_format(format: DxgiFormat) -> Option<vk::Format> {
+    Some(match format {
+        DxgiFormat::R8G8B8A8_UNorm => vk::Format::R8G8B8A8_UNORM,
+        DxgiFormat::R8G8B8A8_UNorm_sRGB => vk::Format::R8G8B8A8_SRGB,
+        DxgiFormat::B8G8R8A8_UNorm => vk::Format::B8G8R8A8_UNORM,
+        DxgiFormat::B8G8R8A8_UNorm_sRGB => vk::Format::B8G8R8A8_SRGB,
+        DxgiFormat::BC1_UNorm => vk::Format::BC1_RGBA_UNORM_BLOCK,
+        DxgiFormat::BC1_UNorm_sRGB => vk::Format::BC1_RGBA_SRGB_BLOCK,
+        DxgiFormat::BC2_UNorm => vk::Format::BC2_UNORM_BLOCK,
+        DxgiFormat::BC2_UNorm_sRGB => vk::Format::BC2_SRGB_BLOCK,
+        DxgiFormat::BC3_UNorm => vk::Format::BC3_UNORM_BLOCK,
+        DxgiFormat::BC3_UNorm_sRGB => vk::Format::BC3_SRGB_BLOCK,
+        DxgiFormat::BC4_UNorm => vk::Format::BC4_UNORM_BLOCK,
+        DxgiFormat::BC5_UNorm => vk::Format::BC5_UNORM_BLOCK,
+        DxgiFormat::BC6H_UF16 => vk::Format::BC6H_UFLOAT_BLOCK,
+        DxgiFormat::BC6H_SF16 => vk::Format::BC6H_SFLOAT_BLOCK,
+        DxgiFormat::BC7_UNorm => vk::Format::BC7_UNORM_BLOCK,
+        DxgiFormat::BC7_UNorm_sRGB => vk::Format::BC7_SRGB_BLOCK,
+        _ => return None,
+    })
+}
+
+fn get_d3d_format(format: D3DFormat) -> Option<vk::Format> {
+    Some(match format {
+        D3DFormat::A8B8G8R8 => vk::Format::R8G8B8A8_UNORM,
+        D3DFormat::A8R8G8B8 => vk::Format::B8G8R8A8_UNORM,
+        D3DFormat::DXT1 => vk::Format::BC1_RGBA_UNORM_BLOCK,
+        D3DFormat::DXT3 => vk::Format::BC2_UNORM_BLOCK,
+        D3DFormat::DXT5 => vk::Format::BC3_UNORM_BLOCK,
+        _ => return None,
+    })
+}
+
+/// The texel block of a format; uncompressed formats have 1x1 blocks.
+#[derive(Copy, Clone, Debug)]
+pub struct BlockInfo {
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u32,
+}
+
+impl BlockInfo {
+    /// The size of one layer of a mip level, in bytes.
+    pub fn level_size(self, width: u32, height: u32) -> usize {
+        (width.div_ceil(self.width) * height.div_ceil(self.height) * self.bytes) as usize
+    }
+
+    /// What buffer offsets of copies must be a multiple of: the block size and 4.
+    pub fn copy_alignment(self) -> u64 {
+        let (mut a, mut b) = (self.bytes as u64, 4);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        self.bytes as u64 * 4 / a
+    }
+}
+
+/// The block layout of the formats textures can be loaded in.
+pub fn get_block_info(format: vk::Format) -> Option<BlockInfo> {
+    let block = |width, height, bytes| BlockInfo {
+        width,
+        height,
+        bytes,
+    };
+
+    Some(match format {
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB => block(1, 1, 4),
+        vk::Format::R16G16B16A16_SFLOAT => block(1, 1, 8),
+        vk::Format::BC1_RGB_UNORM_BLOCK
+        | vk::Format::BC1_RGB_SRGB_BLOCK
+        | vk::Format::BC1_RGBA_UNORM_BLOCK
+        | vk::Format::BC1_RGBA_SRGB_BLOCK
+        | vk::Format::BC4_UNORM_BLOCK
+        | vk::Format::ETC2_R8G8B8_UNORM_BLOCK
+        | vk::Format::ETC2_R8G8B8_SRGB_BLOCK
+        | vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK
+        | vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK
+        | vk::Format::EAC_R11_UNORM_BLOCK => block(4, 4, 8),
+        vk::Format::BC2_UNORM_BLOCK
+        | vk::Format::BC2_SRGB_BLOCK
+        | vk::Format::BC3_UNORM_BLOCK
+        | vk::Format::BC3_SRGB_BLOCK
+        | vk::Format::BC5_UNORM_BLOCK
+        | vk::Format::BC6H_UFLOAT_BLOCK
+        | vk::Format::BC6H_SFLOAT_BLOCK
+        | vk::Format::BC7_UNORM_BLOCK
+        | vk::Format::BC7_SRGB_BLOCK
+        | vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK
+        | vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK
+        | vk::Format::EAC_R11G11_UNORM_BLOCK => block(4, 4, 16),
+        _ => {
+            let (width, height) = get_astc_block(format)?;
+            block(width, height, 16)
+        }
+    })
+}
+
+/// The block size of an ASTC format.
+fn get_astc_block(format: vk::Format) -> Option<(u32, u32)> {
+    Some(match format {
+        vk::Format::ASTC_4X4_UNORM_BLOCK | vk::Format::ASTC_4X4_SRGB_BLOCK => (4, 4),
+        vk::Format::ASTC_5X4_UNORM_BLOCK | vk::Format::ASTC_5X4_SRGB_BLOCK => (5, 4),
+        vk::Format::ASTC_5X5_UNORM_BLOCK | vk::Format::ASTC_5X5_SRGB_BLOCK => (5, 5),
+        vk::Format::ASTC_6X5_UNORM_BLOCK | vk::Format::ASTC_6X5_SRGB_BLOCK => (6, 5),
+        vk::Format::ASTC_6X6_UNORM_BLOCK | vk::Format::ASTC_6X6_SRGB_BLOCK => (6, 6),
+        vk::Format::ASTC_8X5_UNORM_BLOCK | vk::Format::ASTC_8X5_SRGB_BLOCK => (8, 5),
+        vk::Format::ASTC_8X6_UNORM_BLOCK | vk::Format::ASTC_8X6_SRGB_BLOCK => (8, 6),
+        vk::Format::ASTC_8X8_UNORM_BLOCK | vk::Format::ASTC_8X8_SRGB_BLOCK => (8, 8),
+        vk::Format::ASTC_10X5_UNORM_BLOCK | vk::Format::ASTC_10X5_SRGB_BLOCK => (10, 5),
+        vk::Format::ASTC_10X6_UNORM_BLOCK | vk::Format::ASTC_10X6_SRGB_BLOCK => (10, 6),
+        vk::Format::ASTC_10X8_UNORM_BLOCK | vk::Format::ASTC_10X8_SRGB_BLOCK => (10, 8),
+        vk::Format::ASTC_10X10_UNORM_BLOCK | vk::Format::ASTC_10X10_SRGB_BLOCK => (10, 10),
+        vk::Format::ASTC_12X10_UNORM_BLOCK | vk::Format::ASTC_12X10_SRGB_BLOCK => (12, 10),
+        vk::Format::ASTC_12X12_UNORM_BLOCK | vk::Format::ASTC_12X12_SRGB_BLOCK => (12, 12),
+        _ => return None,
+    })
+}
+
+type Decoder = fn(&[u8], usize, usize, &mut [u32]) -> Result<(), &'static str>;
+
+/// The CPU decoder of a block-compressed format.
+fn get_decoder(format: vk::Format) -> Option<Decoder> {
+    use texture2ddecoder::*;
+
+    Some(match format {
+        vk::Format::BC1_RGB_UNORM_BLOCK | vk::Format::BC1_RGB_SRGB_BLOCK => decode_bc1,
+        vk::Format::BC1_RGBA_UNORM_BLOCK | vk::Format::BC1_RGBA_SRGB_BLOCK => decode_bc1a,
+        vk::Format::BC2_UNORM_BLOCK | vk::Format::BC2_SRGB_BLOCK => decode_bc2,
+        vk::Format::BC3_UNORM_BLOCK | vk::Format::BC3_SRGB_BLOCK => decode_bc3,
+        vk::Format::BC4_UNORM_BLOCK => decode_bc4,
+        vk::Format::BC5_UNORM_BLOCK => decode_bc5,
+        // HDR values are clamped to [0, 1].
+        vk::Format::BC6H_UFLOAT_BLOCK => decode_bc6_unsigned,
+        vk::Format::BC6H_SFLOAT_BLOCK => decode_bc6_signed,
+        vk::Format::BC7_UNORM_BLOCK | vk::Format::BC7_SRGB_BLOCK => decode_bc7,
+        vk::Format::ETC2_R8G8B8_UNORM_BLOCK | vk::Format::ETC2_R8G8B8_SRGB_BLOCK => decode_etc2_rgb,
+        vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK | vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK => {
+            decode_etc2_rgba1
+        }
+        vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK | vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK => {
+            decode_etc2_rgba8
+        }
+        vk::Format::EAC_R11_UNORM_BLOCK => decode_eacr,
+        vk::Format::EAC_R11G11_UNORM_BLOCK => decode_eacrg,
+        _ => {
+            let (width, height) = get_astc_block(format)?;
+            return Some(match (width, height) {
+                (4, 4) => |d, w, h, i| decode_astc(d, w, h, 4, 4, i),
+                (5, 4) => |d, w, h, i| decode_astc(d, w, h, 5, 4, i),
+                (5, 5) => |d, w, h, i| decode_astc(d, w, h, 5, 5, i),
+                (6, 5) => |d, w, h, i| decode_astc(d, w, h, 6, 5, i),
+                (6, 6) => |d, w, h, i| decode_astc(d, w, h, 6, 6, i),
+                (8, 5) => |d, w, h, i| decode_astc(d, w, h, 8, 5, i),
+                (8, 6) => |d, w, h, i| decode_astc(d, w, h, 8, 6, i),
+                (8, 8) => |d, w, h, i| decode_astc(d, w, h, 8, 8, i),
+                (10, 5) => |d, w, h, i| decode_astc(d, w, h, 10, 5, i),
+                (10, 6) => |d, w, h, i| decode_astc(d, w, h, 10, 6, i),
+                (10, 8) => |d, w, h, i| decode_astc(d, w, h, 10, 8, i),
+                (10, 10) => |d, w, h, i| decode_astc(d, w, h, 10, 10, i),
+                (12, 10) => |d, w, h, i| decode_astc(d, w, h, 12, 10, i),
+                _ => |d, w, h, i| decode_astc(d, w, h, 12, 12, i),
+            });
+        }
+    })
+}
+
+/// Formats that come in both a `_UNORM` and an `_SRGB` variant.
+pub const FORMAT_PAIRS: &[(vk::Format, vk::Format)] = &[
+    (vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB),
+    (vk::Format::B8G8R8A8_UNORM, vk::Format::B8G8R8A8_SRGB),
+    (
+        vk::Format::BC1_RGB_UNORM_BLOCK,
+        vk::Format::BC1_RGB_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::BC1_RGBA_UNORM_BLOCK,
+        vk::Format::BC1_RGBA_SRGB_BLOCK,
+    ),
+    (vk::Format::BC2_UNORM_BLOCK, vk::Format::BC2_SRGB_BLOCK),
+    (vk::Format::BC3_UNORM_BLOCK, vk::Format::BC3_SRGB_BLOCK),
+    (vk::Format::BC7_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK),
+    (
+        vk::Format::ETC2_R8G8B8_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_4X4_UNORM_BLOCK,
+        vk::Format::ASTC_4X4_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_5X4_UNORM_BLOCK,
+        vk::Format::ASTC_5X4_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_5X5_UNORM_BLOCK,
+        vk::Format::ASTC_5X5_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_6X5_UNORM_BLOCK,
+        vk::Format::ASTC_6X5_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_6X6_UNORM_BLOCK,
+        vk::Format::ASTC_6X6_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_8X5_UNORM_BLOCK,
+        vk::Format::ASTC_8X5_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_8X6_UNORM_BLOCK,
+        vk::Format::ASTC_8X6_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_8X8_UNORM_BLOCK,
+        vk::Format::ASTC_8X8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_10X5_UNORM_BLOCK,
+        vk::Format::ASTC_10X5_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_10X6_UNORM_BLOCK,
+        vk::Format::ASTC_10X6_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_10X8_UNORM_BLOCK,
+        vk::Format::ASTC_10X8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_10X10_UNORM_BLOCK,
+        vk::Format::ASTC_10X10_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_12X10_UNORM_BLOCK,
+        vk::Format::ASTC_12X10_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_12X12_UNORM_BLOCK,
+        vk::Format::ASTC_12X12_SRGB_BLOCK,
+    ),
+];
+
+/// Whether the device can sample and linearly filter a format with optimal tiling.
+pub unsafe fn is_format_supported(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    format: vk::Format,
+) -> bool {
+    let required =
+        vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR;
+
+    instance
+        .get_physical_device_format_properties(physical_device, format)
+        .optimal_tiling_features
+        .contains(required)
+}
+
+/// Loads a texture file, decompressing it on the CPU if the device can't sample its format.
+///
+/// Files that don't say whether their texels are sRGB are loaded in `color_space`; files that
+/// do keep their format, with a warning if it contradicts `color_space`.
+pub unsafe fn load_texture_file(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    path: &Path,
+    color_space: ColorSpace,
+) -> Result<TextureFile> {
+    let mut file = TextureFile::load(path)?;
+    if file.ambiguous_color_space {
+        file.format = color_space.apply(file.format);
+    } else if ColorSpace::of(file.format).is_some_and(|c| c != color_space) {
+        warn!(
+            "Texture `{}` is stored as {:?}, ignoring the requested {:?} color space.",
+            path.display(),
+            file.format,
+            color_space
+        );
+    }
+
+    if is_format_supported(instance, physical_device, file.format) {
+        return Ok(file);
+    }
+
+    if !file.is_compressed() {
+        return Err(anyhow!(
+            "Texture `{}` uses {:?}, which the device can't sample.",
+            path.display(),
+            file.format
+        ));
+    }
+
+    warn!(
+        "{:?} is not supported by the device, decompressing `{}` on the CPU.",
+        file.format,
+        path.display()
+    );
+
+    if matches!(
+        file.format,
+        vk::Format::BC6H_UFLOAT_BLOCK | vk::Format::BC6H_SFLOAT_BLOCK
+    ) {
+        warn!(
+            "`{}` is HDR, but is decompressed to 8 bits with values clamped to [0, 1].",
+            path.display()
+        );
+    }
+    file.decompress()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_size() {
+        let bc1 = get_block_info(vk::Format::BC1_RGBA_UNORM_BLOCK).unwrap();
+        assert_eq!(bc1.level_size(8, 8), 4 * 8);
+        assert_eq!(bc1.level_size(5, 1), 2 * 8);
+
+        let astc = get_block_info(vk::Format::ASTC_10X6_SRGB_BLOCK).unwrap();
+        assert_eq!(astc.level_size(20, 7), 2 * 2 * 16);
+    }
+
+    #[test]
+    fn test_copy_alignment() {
+        for (format, alignment) in [
+            (vk::Format::R8G8B8A8_SRGB, 4),
+            (vk::Format::R16G16B16A16_SFLOAT, 8),
+            (vk::Format::BC1_RGB_UNORM_BLOCK, 8),
+            (vk::Format::BC7_UNORM_BLOCK, 16),
+            (vk::Format::ASTC_12X12_UNORM_BLOCK, 16),
+        ] {
+            let block = get_block_info(format).unwrap();
+            assert_eq!(block.copy_alignment(), alignment, "{:?}", format);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::texture::color_space::ColorSpace;
+use crate::texture::container::{self, TextureFile};
+use crate::texture::manager::{self, Texture};
+use crate::texture::mipmap::{self, MipmapFilter};
+use crate::texture::{decode, image};
+use crate::AppData;
+
+/// File names of the faces in a cubemap directory, in layer order (+X, -X, +Y, -Y, +Z, -Z).
+const FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// Loads a cubemap with a `CUBE` view and a descriptor set binding it to set 1.
+///
+/// `path` is a directory with six faces named after `FACE_NAMES`, a cubemap KTX2 or DDS
+/// file, or an equirectangular image in any format `decode` reads, like a Radiance HDR.
+pub unsafe fn create_cubemap(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    path: &Path,
+) -> Result<Texture> {
+    let file = if path.is_dir() {
+        load_faces(data, path)?
+    } else if container::is_texture_file(path) {
+        let physical_device = data.setup_data.physical_device;
+        let file = container::load_texture_file(instance, physical_device, path, ColorSpace::Srgb)?;
+        if !file.cube {
+            return Err(anyhow!("`{}` is not a cubemap.", path.display()));
+        } else if file.array_layers != 6 {
+            return Err(anyhow!(
+                "Cubemap arrays like `{}` are not supported.",
+                path.display()
+            ));
+        }
+        file
+    } else {
+        load_equirectangular(data, path)?
+    };
+
+    let (image, image_memory, format, mip_levels) =
+        image::create_texture_file_image(instance, device, data, &file)?;
+
+    let image_view = image::create_image_view(
+        device,
+        image,
+        format,
+        vk::ImageAspectFlags::COLOR,
+        mip_levels,
+        vk::ImageViewType::CUBE,
+    )?;
+
+    let descriptor_set = manager::create_texture_descriptor_set(
+        device,
+        data,
+        image_view,
+        data.texture_data.texture_sampler,
+    )?;
+
+    info!(
+        "Loaded cubemap `{}` ({}x{}).",
+        path.display(),
+        file.width,
+        file.height
+    );
+
+    Ok(Texture {
+        image,
+        image_memory,
+        image_view,
+        format,
+        color_space: ColorSpace::Srgb,
+        mip_levels,
+        descriptor_set,
+    })
+}
+
+fn load_faces(data: &AppData, path: &Path) -> Result<TextureFile> {
+    let images = FACE_NAMES
+        .iter()
+        .map(|name| {
+            let face = find_face(path, name)?;
+            let image = decode::decode_image(&face)?;
+
+            if image.width != image.height {
+                return Err(anyhow!(
+                    "Cubemap faces must be square (`{}` is {}x{}).",
+                    face.display(),
+                    image.width,
+                    image.height
+                ));
+            }
+
+            Ok(image)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let size = images[0].width;
+    if images.iter().any(|i| i.width != size) {
+        return Err(anyhow!("Cubemap faces must all be the same size."));
+    }
+
+    let hdr = images.iter().any(|i| i.is_hdr());
+    let faces = images
+        .iter()
+        .map(|i| i.to_linear(ColorSpace::Srgb))
+        .collect::<Vec<_>>();
+
+    Ok(create_cube_file(data, faces, size, hdr))
+}
+
+/// The image in `directory` named `name`, whatever its extension.
+fn find_face(directory: &Path, name: &str) -> Result<PathBuf> {
+    fs::read_dir(directory)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .find(|p| p.file_stem().is_some_and(|s| s == name))
+        .ok_or_else(|| {
+            anyhow!(
+                "Cubemap face `{}` not found in `{}`.",
+                name,
+                directory.display()
+            )
+        })
+}
+
+fn load_equirectangular(data: &AppData, path: &Path) -> Result<TextureFile> {
+    let image = decode::decode_image(path)?;
+    let (width, height) = (image.width, image.height);
+    let texels = image.to_linear(ColorSpace::Srgb);
+
+    // A quarter of the width keeps about the same texel density around the equator.
+    let size = (width / 4).max(1);
+    let faces = (0..6)
+        .map(|face| {
+            let mut face_texels = Vec::with_capacity((size * size) as usize);
+            for y in 0..size {
+                for x in 0..size {
+                    let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+                    let v = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+                    let direction = get_face_direction(face, u, v);
+                    face_texels.push(sample_equirectangular(&texels, width, height, direction));
+                }
+            }
+            face_texels
+        })
+        .collect::<Vec<_>>();
+
+    Ok(create_cube_file(data, faces, size, image.is_hdr()))
+}
+
+/// The direction through a texel of a cube face, with `u` and `v` in `[-1, 1]`.
+fn get_face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+/// Bilinearly samples an equirectangular image in the given direction.
+fn sample_equirectangular(
+    texels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    direction: [f32; 3],
+) -> [f32; 4] {
+    let [x, y, z] = direction;
+    let length = (x * x + y * y + z * z).sqrt();
+
+    let longitude = x.atan2(-z);
+    let latitude = (y / length).asin();
+
+    let s = (0.5 + longitude / (2.0 * PI)) * width as f32 - 0.5;
+    let t = (0.5 - latitude / PI) * height as f32 - 0.5;
+
+    let (s0, t0) = (s.floor(), t.floor());
+    let (fs, ft) = (s - s0, t - t0);
+
+    let texel = |s: f32, t: f32| {
+        // Longitude wraps around, latitude stops at the poles.
+        let s = (s as i64).rem_euclid(width as i64) as u32;
+        let t = (t as i64).clamp(0, height as i64 - 1) as u32;
+        texels[(t * width + s) as usize]
+    };
+
+    let (a, b) = (texel(s0, t0), texel(s0 + 1.0, t0));
+    let (c, d) = (texel(s0, t0 + 1.0), texel(s0 + 1.0, t0 + 1.0));
+
+    let mut result = [0.0; 4];
+    for (i, value) in result.iter_mut().enumerate() {
+        let top = a[i] * (1.0 - fs) + b[i] * fs;
+        let bottom = c[i] * (1.0 - fs) + d[i] * fs;
+        *value = top * (1.0 - ft) + bottom * ft;
+    }
+
+    result
+}
+
+/// Builds a cubemap with a full mip chain from six square faces of linear texels.
+fn create_cube_file(
+    data: &AppData,
+    faces: Vec<Vec<[f32; 4]>>,
+    size: u32,
+    hdr: bool,
+) -> TextureFile {
+    // Blits only cover a single layer, so cubemap mips are always generated on the CPU.
+    let filter = match data.config.mipmap_filter {
+        MipmapFilter::Blit => MipmapFilter::Box,
+        filter => filter,
+    };
+
+    let face_levels = faces
+        .into_iter()
+        .map(|f| mipmap::generate_chain(f, size, size, filter))
+        .collect::<Vec<_>>();
+
+    let mip_levels = face_levels[0].len();
+    let levels = (0..mip_levels)
+        .map(|level| {
+            face_levels
+                .iter()
+                .flat_map(|f| decode::encode_texels(&f[level], hdr))
+                .collect()
+        })
+        .collect();
+
+    TextureFile {
+        format: if hdr {
+            vk::Format::R16G16B16A16_SFLOAT
+        } else {
+            vk::Format::R8G8B8A8_SRGB
+        },
+        width: size,
+        height: size,
+        mip_levels: mip_levels as u32,
+        array_layers: 6,
+        cube: true,
+        ambiguous_color_space: false,
+        levels,
+    }
+}
@@ -0,0 +1,234 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use half::f16;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::texture::color_space::ColorSpace;
+use crate::texture::mipmap::{self, MipmapFilter};
+
+/// The file formats textures are loaded from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SourceFormat {
+    Png,
+    Jpeg,
+    Tga,
+    Bmp,
+    Hdr,
+    Ktx2,
+    Dds,
+}
+
+/// Bytes needed to tell the formats apart.
+const MAGIC_LENGTH: usize = 12;
+
+/// Detects the format of a file from its first bytes.
+///
+/// TGA files don't start with a signature, so they are recognized by their extension.
+pub fn detect_format(bytes: &[u8], path: &Path) -> Result<SourceFormat> {
+    const KTX2: &[u8] = &[0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB];
+
+    let format = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        SourceFormat::Png
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        SourceFormat::Jpeg
+    } else if bytes.starts_with(b"BM") {
+        SourceFormat::Bmp
+    } else if bytes.starts_with(b"#?RADIANCE") || bytes.starts_with(b"#?RGBE") {
+        SourceFormat::Hdr
+    } else if bytes.starts_with(KTX2) {
+        SourceFormat::Ktx2
+    } else if bytes.starts_with(b"DDS ") {
+        SourceFormat::Dds
+    } else if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("tga"))
+    {
+        SourceFormat::Tga
+    } else {
+        return Err(anyhow!("`{}` is not a supported image.", path.display()));
+    };
+
+    Ok(format)
+}
+
+/// Detects the format of a file, reading only its first bytes.
+pub fn detect_file_format(path: &Path) -> Result<SourceFormat> {
+    let mut bytes = Vec::with_capacity(MAGIC_LENGTH);
+    File::open(path)?
+        .take(MAGIC_LENGTH as u64)
+        .read_to_end(&mut bytes)?;
+
+    detect_format(&bytes, path)
+}
+
+/// Decoded texels, 8-bit for LDR images and floating point for HDR ones.
+#[derive(Clone, Debug)]
+pub enum Pixels {
+    Rgba8(Vec<u8>),
+    Rgba32F(Vec<f32>),
+}
+
+/// An image decoded from a PNG, JPEG, TGA, BMP or Radiance HDR file.
+#[derive(Clone, Debug)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Pixels,
+}
+
+impl DecodedImage {
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.pixels, Pixels::Rgba32F(_))
+    }
+
+    /// The format the image is uploaded in; HDR images are halved to 16-bit floats, which are
+    /// always linear.
+    pub fn format(&self, color_space: ColorSpace) -> vk::Format {
+        match self.pixels {
+            Pixels::Rgba8(_) => color_space.apply(vk::Format::R8G8B8A8_SRGB),
+            Pixels::Rgba32F(_) => vk::Format::R16G16B16A16_SFLOAT,
+        }
+    }
+
+    /// The texels in `format`, ready to upload.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match &self.pixels {
+            Pixels::Rgba8(pixels) => pixels.clone(),
+            Pixels::Rgba32F(pixels) => encode_rgba16f(pixels),
+        }
+    }
+
+    /// The texels in linear space.
+    pub fn to_linear(&self, color_space: ColorSpace) -> Vec<[f32; 4]> {
+        match &self.pixels {
+            Pixels::Rgba8(pixels) => mipmap::decode_rgba8(pixels, color_space.is_srgb()),
+            Pixels::Rgba32F(pixels) => pixels
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+        }
+    }
+
+    /// Builds the mip chain on the CPU, each level in `format`.
+    ///
+    /// sRGB images are filtered in linear space, linear ones as they are.
+    pub fn generate_mipmaps(&self, filter: MipmapFilter, color_space: ColorSpace) -> Vec<Vec<u8>> {
+        match &self.pixels {
+            Pixels::Rgba8(pixels) => mipmap::generate_mipmaps(
+                pixels,
+                self.width,
+                self.height,
+                color_space.is_srgb(),
+                filter,
+            ),
+            Pixels::Rgba32F(_) => {
+                let texels = self.to_linear(color_space);
+                mipmap::generate_chain(texels, self.width, self.height, filter)
+                    .iter()
+                    .map(|l| encode_texels(l, true))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Decodes an image file of any format but KTX2 and DDS.
+pub fn decode_image(path: &Path) -> Result<DecodedImage> {
+    let bytes = fs::read(path)?;
+
+    let format = match detect_format(&bytes, path)? {
+        SourceFormat::Png => image::ImageFormat::Png,
+        SourceFormat::Jpeg => image::ImageFormat::Jpeg,
+        SourceFormat::Tga => image::ImageFormat::Tga,
+        SourceFormat::Bmp => image::ImageFormat::Bmp,
+        SourceFormat::Hdr => image::ImageFormat::Hdr,
+        format => {
+            return Err(anyhow!(
+                "`{}` is a {:?} file, which isn't decoded to texels.",
+                path.display(),
+                format
+            ))
+        }
+    };
+
+    let image = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| anyhow!("Failed to decode `{}`: {}", path.display(), e))?;
+
+    let (width, height) = (image.width(), image.height());
+    let pixels = if format == image::ImageFormat::Hdr {
+        Pixels::Rgba32F(image.into_rgba32f().into_raw())
+    } else {
+        Pixels::Rgba8(image.into_rgba8().into_raw())
+    };
+
+    Ok(DecodedImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Linear texels as 8-bit sRGB, or as 16-bit floats if `hdr`.
+pub fn encode_texels(texels: &[[f32; 4]], hdr: bool) -> Vec<u8> {
+    if hdr {
+        encode_rgba16f(texels.as_flattened())
+    } else {
+        mipmap::encode_rgba8(texels, true)
+    }
+}
+
+fn encode_rgba16f(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| f16::from_f32(*v).to_le_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(bytes: &[u8], path: &str) -> Result<SourceFormat> {
+        detect_format(bytes, Path::new(path))
+    }
+
+    #[test]
+    fn test_magic_signatures() {
+        let signatures: &[(&[u8], SourceFormat)] = &[
+            (b"\x89PNG\r\n\x1a\n\0\0\0\x0d", SourceFormat::Png),
+            (&[0xFF, 0xD8, 0xFF, 0xE0], SourceFormat::Jpeg),
+            (b"BM\x36\0\0\0", SourceFormat::Bmp),
+            (b"#?RADIANCE\n", SourceFormat::Hdr),
+            (b"#?RGBE\n", SourceFormat::Hdr),
+            (
+                &[0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n'],
+                SourceFormat::Ktx2,
+            ),
+            (b"DDS \x7c\0\0\0", SourceFormat::Dds),
+        ];
+
+        // The signature wins over a misleading extension.
+        for (bytes, format) in signatures {
+            assert_eq!(detect(bytes, "texture.tga").unwrap(), *format);
+        }
+    }
+
+    #[test]
+    fn test_tga_extension() {
+        let header = [0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(detect(&header, "texture.tga").unwrap(), SourceFormat::Tga);
+        assert_eq!(detect(&header, "TEXTURE.TGA").unwrap(), SourceFormat::Tga);
+        assert!(detect(&header, "texture.png").is_err());
+        assert!(detect(&header, "texture").is_err());
+    }
+
+    #[test]
+    fn test_unknown_bytes() {
+        assert!(detect(b"GIF89a", "texture.gif").is_err());
+        assert!(detect(&[0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB], "a.ktx").is_err());
+        assert!(detect(&[], "texture.png").is_err());
+    }
+}
@@ -1,103 +1,317 @@
-use std::fs::File;
+use std::path::Path;
 use std::ptr::copy_nonoverlapping as memcpy;
 
 use anyhow::{anyhow, Result};
+use log::*;
 use vulkanalia::prelude::v1_0::*;
 
 use crate::setup::device;
+use crate::texture::color_space::ColorSpace;
+use crate::texture::container::{self, TextureFile};
+use crate::texture::decode;
+use crate::texture::mipmap::MipmapFilter;
 use crate::vertex::vertex;
 use crate::{begin_single_time_commands, end_single_time_commands, AppData};
 
+/// Loads a texture file into a sampled image.
+///
+/// PNG, JPEG, TGA, BMP and Radiance HDR images get a generated mip chain, while KTX2 and DDS
+/// files are uploaded with the mips and layers they contain. Returns the image, its memory, its
+/// format and its mip level count.
+///
+/// 8-bit texels are uploaded in the `_SRGB` or `_UNORM` format of `color_space`.
 pub unsafe fn create_texture_image(
     instance: &Instance,
     device: &Device,
-    data: &mut AppData,
-) -> Result<()> {
-    let image = File::open("/home/mhl/vulkanalia_tutorial/src/texture/resources/viking_room.png")?;
-
-    let decoder = png::Decoder::new(image);
-    let mut reader = decoder.read_info()?;
+    data: &AppData,
+    path: &Path,
+    color_space: ColorSpace,
+) -> Result<(vk::Image, vk::DeviceMemory, vk::Format, u32)> {
+    if container::is_texture_file(path) {
+        let physical_device = data.setup_data.physical_device;
+        let file = container::load_texture_file(instance, physical_device, path, color_space)?;
+
+        // Textures are sampled through 2D views, which would leave every other layer unused.
+        if file.array_layers > 1 {
+            return Err(anyhow!(
+                "Texture `{}` has {} layers, but only single-layer textures are supported.",
+                path.display(),
+                file.array_layers
+            ));
+        }
 
-    let mut pixels = vec![0; reader.info().raw_bytes()];
-    reader.next_frame(&mut pixels)?;
+        return create_texture_file_image(instance, device, data, &file);
+    }
 
-    let size = reader.info().raw_bytes() as u64;
-    let (width, height) = reader.info().size();
+    let image = decode::decode_image(path)?;
+    let (width, height) = (image.width, image.height);
 
-    if width != 1024 || height != 1024 || reader.info().color_type != png::ColorType::Rgba {
-        panic!("Invalid texture image.");
-    }
+    let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
 
-    data.texture_data.mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+    let format = image.format(color_space);
+    let filter = match data.config.mipmap_filter {
+        MipmapFilter::Blit if !supports_linear_blit(instance, data, format) => {
+            warn!(
+                "{:?} does not support linear blitting, generating mips on the CPU.",
+                format
+            );
+            MipmapFilter::Box
+        }
+        filter => filter,
+    };
 
-    let (staging_buffer, staging_buffer_memory) = vertex::create_buffer(
-        instance,
-        device,
-        data,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
-    )?;
+    if filter != MipmapFilter::Blit {
+        let file = TextureFile {
+            format,
+            width,
+            height,
+            mip_levels,
+            array_layers: 1,
+            cube: false,
+            ambiguous_color_space: false,
+            levels: image.generate_mipmaps(filter, color_space),
+        };
 
-    let memory = device.map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())?;
+        return create_texture_file_image(instance, device, data, &file);
+    }
 
-    memcpy(pixels.as_ptr(), memory.cast(), pixels.len());
+    let pixels = image.to_bytes();
+    let size = pixels.len() as u64;
 
-    device.unmap_memory(staging_buffer_memory);
+    let (staging_buffer, staging_buffer_memory) =
+        create_staging_buffer(instance, device, data, size, |memory| {
+            memcpy(pixels.as_ptr(), memory, pixels.len());
+        })?;
 
-    let (texture_image, texture_image_memory) = create_image(
+    let (texture_image, texture_image_memory) = match create_image(
         instance,
         device,
         data,
         width,
         height,
-        data.texture_data.mip_levels,
+        mip_levels,
+        1,
+        vk::ImageCreateFlags::empty(),
         vk::SampleCountFlags::_1,
-        vk::Format::R8G8B8A8_SRGB,
+        format,
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::SAMPLED
             | vk::ImageUsageFlags::TRANSFER_DST
             | vk::ImageUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-    )?;
+    ) {
+        Ok(image) => image,
+        Err(e) => {
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_buffer_memory, None);
+            return Err(e);
+        }
+    };
+
+    let region = get_copy_region(0, 0, 1, width, height);
+    let result = upload_image(
+        device,
+        data,
+        staging_buffer,
+        texture_image,
+        format,
+        &[region],
+        mip_levels,
+        1,
+    )
+    .and_then(|_| {
+        generate_mipmaps(
+            instance,
+            device,
+            data,
+            texture_image,
+            format,
+            width,
+            height,
+            mip_levels,
+        )
+    });
 
-    data.texture_data.texture_image = texture_image;
-    data.texture_data.texture_image_memory = texture_image_memory;
+    device.destroy_buffer(staging_buffer, None);
+    device.free_memory(staging_buffer_memory, None);
 
-    transition_image_layout(
+    if let Err(e) = result {
+        device.destroy_image(texture_image, None);
+        device.free_memory(texture_image_memory, None);
+        return Err(e);
+    }
+
+    Ok((texture_image, texture_image_memory, format, mip_levels))
+}
+
+/// Uploads every mip level and layer of a texture file with a single staging copy.
+///
+/// Cubemap files get a cube-compatible image.
+pub unsafe fn create_texture_file_image(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    file: &TextureFile,
+) -> Result<(vk::Image, vk::DeviceMemory, vk::Format, u32)> {
+    let alignment = container::get_block_info(file.format)
+        .ok_or_else(|| anyhow!("Textures can't be uploaded in {:?}.", file.format))?
+        .copy_alignment();
+
+    let mut offsets = Vec::with_capacity(file.levels.len());
+    let mut size = 0u64;
+    for level in &file.levels {
+        size = size.next_multiple_of(alignment);
+        offsets.push(size);
+        size += level.len() as u64;
+    }
+
+    let (staging_buffer, staging_buffer_memory) =
+        create_staging_buffer(instance, device, data, size, |memory| {
+            for (level, offset) in file.levels.iter().zip(&offsets) {
+                memcpy(level.as_ptr(), memory.add(*offset as usize), level.len());
+            }
+        })?;
+
+    let (texture_image, texture_image_memory) = match create_image(
+        instance,
         device,
         data,
-        data.texture_data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
-        vk::ImageLayout::UNDEFINED,
-        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        data.texture_data.mip_levels,
-    )?;
+        file.width,
+        file.height,
+        file.mip_levels,
+        file.array_layers,
+        if file.cube {
+            vk::ImageCreateFlags::CUBE_COMPATIBLE
+        } else {
+            vk::ImageCreateFlags::empty()
+        },
+        vk::SampleCountFlags::_1,
+        file.format,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    ) {
+        Ok(image) => image,
+        Err(e) => {
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_buffer_memory, None);
+            return Err(e);
+        }
+    };
 
-    copy_buffer_to_image(
+    let regions = offsets
+        .iter()
+        .enumerate()
+        .map(|(level, offset)| {
+            let (width, height) = file.level_extent(level as u32);
+            get_copy_region(*offset, level as u32, file.array_layers, width, height)
+        })
+        .collect::<Vec<_>>();
+
+    let result = upload_image(
         device,
         data,
         staging_buffer,
-        data.texture_data.texture_image,
-        width,
-        height,
-    )?;
+        texture_image,
+        file.format,
+        &regions,
+        file.mip_levels,
+        file.array_layers,
+    )
+    .and_then(|_| {
+        transition_image_layout(
+            device,
+            data,
+            texture_image,
+            file.format,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            file.mip_levels,
+            file.array_layers,
+        )
+    });
 
     device.destroy_buffer(staging_buffer, None);
     device.free_memory(staging_buffer_memory, None);
 
-    generate_mipmaps(
+    if let Err(e) = result {
+        device.destroy_image(texture_image, None);
+        device.free_memory(texture_image_memory, None);
+        return Err(e);
+    }
+
+    if file.is_compressed() {
+        let uncompressed = (file.width * file.height * 4 * file.array_layers) as f32 * 4.0 / 3.0;
+        info!(
+            "Uploaded {:?} texture ({} KiB, {:.0} KiB as RGBA8).",
+            file.format,
+            size / 1024,
+            uncompressed / 1024.0
+        );
+    }
+
+    Ok((
+        texture_image,
+        texture_image_memory,
+        file.format,
+        file.mip_levels,
+    ))
+}
+
+/// Copies `regions` of a staging buffer into a new image, leaving it as a transfer destination.
+unsafe fn upload_image(
+    device: &Device,
+    data: &AppData,
+    buffer: vk::Buffer,
+    image: vk::Image,
+    format: vk::Format,
+    regions: &[vk::BufferImageCopy],
+    mip_levels: u32,
+    array_layers: u32,
+) -> Result<()> {
+    transition_image_layout(
+        device,
+        data,
+        image,
+        format,
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        mip_levels,
+        array_layers,
+    )?;
+
+    copy_buffer_to_image(device, data, buffer, image, regions)
+}
+
+/// Creates a host-visible buffer to copy from, filled by `write`.
+unsafe fn create_staging_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    size: u64,
+    write: impl FnOnce(*mut u8),
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    let (buffer, buffer_memory) = vertex::create_buffer(
         instance,
         device,
         data,
-        data.texture_data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
-        width,
-        height,
-        data.texture_data.mip_levels,
+        size,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
     )?;
 
-    Ok(())
+    match device.map_memory(buffer_memory, 0, size, vk::MemoryMapFlags::empty()) {
+        Ok(memory) => {
+            write(memory.cast());
+            device.unmap_memory(buffer_memory);
+            Ok((buffer, buffer_memory))
+        }
+        Err(e) => {
+            device.destroy_buffer(buffer, None);
+            device.free_memory(buffer_memory, None);
+            Err(anyhow!(e))
+        }
+    }
 }
 
 unsafe fn create_image(
@@ -107,6 +321,8 @@ unsafe fn create_image(
     width: u32,
     height: u32,
     mip_levels: u32,
+    array_layers: u32,
+    flags: vk::ImageCreateFlags,
     samples: vk::SampleCountFlags,
     format: vk::Format,
     tiling: vk::ImageTiling,
@@ -114,6 +330,7 @@ unsafe fn create_image(
     properties: vk::MemoryPropertyFlags,
 ) -> Result<(vk::Image, vk::DeviceMemory)> {
     let info = vk::ImageCreateInfo::builder()
+        .flags(flags)
         .image_type(vk::ImageType::_2D)
         .extent(vk::Extent3D {
             width,
@@ -121,7 +338,7 @@ unsafe fn create_image(
             depth: 1,
         })
         .mip_levels(mip_levels)
-        .array_layers(1)
+        .array_layers(array_layers)
         .format(format)
         .tiling(tiling)
         .initial_layout(vk::ImageLayout::UNDEFINED)
@@ -133,18 +350,32 @@ unsafe fn create_image(
 
     let requirements = device.get_image_memory_requirements(image);
 
+    let memory_type_index =
+        match device::get_memory_type_index(instance, data, properties, requirements) {
+            Ok(memory_type_index) => memory_type_index,
+            Err(e) => {
+                device.destroy_image(image, None);
+                return Err(e);
+            }
+        };
+
     let info = vk::MemoryAllocateInfo::builder()
         .allocation_size(requirements.size)
-        .memory_type_index(device::get_memory_type_index(
-            instance,
-            data,
-            properties,
-            requirements,
-        )?);
+        .memory_type_index(memory_type_index);
 
-    let image_memory = device.allocate_memory(&info, None)?;
+    let image_memory = match device.allocate_memory(&info, None) {
+        Ok(image_memory) => image_memory,
+        Err(e) => {
+            device.destroy_image(image, None);
+            return Err(anyhow!(e));
+        }
+    };
 
-    device.bind_image_memory(image, image_memory, 0)?;
+    if let Err(e) = device.bind_image_memory(image, image_memory, 0) {
+        device.destroy_image(image, None);
+        device.free_memory(image_memory, None);
+        return Err(anyhow!(e));
+    }
 
     Ok((image, image_memory))
 }
@@ -157,6 +388,7 @@ unsafe fn transition_image_layout(
     old_layout: vk::ImageLayout,
     new_layout: vk::ImageLayout,
     mip_levels: u32,
+    array_layers: u32,
 ) -> Result<()> {
     let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) =
         match (old_layout, new_layout) {
@@ -182,7 +414,10 @@ unsafe fn transition_image_layout(
             _ => return Err(anyhow!("Unsupported image layout transition!")),
         };
 
-    let command_buffer = begin_single_time_commands(device, data, data.vertex_data.command_pool)?;
+    // Images are exclusive to the graphics queue family, which samples them, so their layout
+    // transitions and uploads run there instead of on the transfer queue.
+    let command_pool = data.drawing_data.command_pool;
+    let command_buffer = begin_single_time_commands(device, data, command_pool)?;
 
     let aspect_mask = if new_layout == vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL {
         match format {
@@ -200,7 +435,7 @@ unsafe fn transition_image_layout(
         .base_mip_level(0)
         .level_count(mip_levels)
         .base_array_layer(0)
-        .layer_count(1);
+        .layer_count(array_layers);
 
     let barrier = vk::ImageMemoryBarrier::builder()
         .old_layout(old_layout)
@@ -226,31 +461,29 @@ unsafe fn transition_image_layout(
         device,
         data,
         command_buffer,
-        data.vertex_data.command_pool,
-        data.setup_data.transfer_queue,
+        command_pool,
+        data.setup_data.graphics_queue,
     )?;
 
     Ok(())
 }
 
-unsafe fn copy_buffer_to_image(
-    device: &Device,
-    data: &AppData,
-    buffer: vk::Buffer,
-    image: vk::Image,
+/// A copy of tightly packed texels at `offset` into every layer of a mip level.
+fn get_copy_region(
+    offset: u64,
+    mip_level: u32,
+    array_layers: u32,
     width: u32,
     height: u32,
-) -> Result<()> {
-    let command_buffer = begin_single_time_commands(device, data, data.vertex_data.command_pool)?;
-
+) -> vk::BufferImageCopy {
     let subresource = vk::ImageSubresourceLayers::builder()
         .aspect_mask(vk::ImageAspectFlags::COLOR)
-        .mip_level(0)
+        .mip_level(mip_level)
         .base_array_layer(0)
-        .layer_count(1);
+        .layer_count(array_layers);
 
-    let region = vk::BufferImageCopy::builder()
-        .buffer_offset(0)
+    vk::BufferImageCopy::builder()
+        .buffer_offset(offset)
         .buffer_row_length(0)
         .buffer_image_height(0)
         .image_subresource(subresource)
@@ -259,62 +492,38 @@ unsafe fn copy_buffer_to_image(
             width,
             height,
             depth: 1,
-        });
+        })
+        .build()
+}
+
+unsafe fn copy_buffer_to_image(
+    device: &Device,
+    data: &AppData,
+    buffer: vk::Buffer,
+    image: vk::Image,
+    regions: &[vk::BufferImageCopy],
+) -> Result<()> {
+    let command_buffer = begin_single_time_commands(device, data, data.drawing_data.command_pool)?;
 
     device.cmd_copy_buffer_to_image(
         command_buffer,
         buffer,
         image,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        &[region],
+        regions,
     );
 
     end_single_time_commands(
         device,
         data,
         command_buffer,
-        data.vertex_data.command_pool,
-        data.setup_data.transfer_queue,
-    )?;
-
-    Ok(())
-}
-
-pub unsafe fn create_texture_image_view(device: &Device, data: &mut AppData) -> Result<()> {
-    data.texture_data.texture_image_view = create_image_view(
-        device,
-        data.texture_data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
-        vk::ImageAspectFlags::COLOR,
-        data.texture_data.mip_levels,
+        data.drawing_data.command_pool,
+        data.setup_data.graphics_queue,
     )?;
 
     Ok(())
 }
 
-pub unsafe fn create_texture_sampler(device: &Device, data: &mut AppData) -> Result<()> {
-    let info = vk::SamplerCreateInfo::builder()
-        .mag_filter(vk::Filter::LINEAR)
-        .min_filter(vk::Filter::LINEAR)
-        .address_mode_u(vk::SamplerAddressMode::REPEAT)
-        .address_mode_v(vk::SamplerAddressMode::REPEAT)
-        .address_mode_w(vk::SamplerAddressMode::REPEAT)
-        .anisotropy_enable(true)
-        .max_anisotropy(16.0)
-        .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
-        .unnormalized_coordinates(false)
-        .compare_enable(false)
-        .compare_op(vk::CompareOp::ALWAYS)
-        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
-        .min_lod(0.0)
-        .max_lod(data.texture_data.mip_levels as f32)
-        .mip_lod_bias(0.0);
-
-    data.texture_data.texture_sampler = device.create_sampler(&info, None)?;
-
-    Ok(())
-}
-
 pub unsafe fn create_depth_objects(
     instance: &Instance,
     device: &Device,
@@ -329,6 +538,8 @@ pub unsafe fn create_depth_objects(
         data.presentation_data.swapchain_extent.width,
         data.presentation_data.swapchain_extent.height,
         1,
+        1,
+        vk::ImageCreateFlags::empty(),
         data.texture_data.msaa_samples,
         format,
         vk::ImageTiling::OPTIMAL,
@@ -346,6 +557,7 @@ pub unsafe fn create_depth_objects(
         format,
         vk::ImageAspectFlags::DEPTH,
         1,
+        vk::ImageViewType::_2D,
     )?;
 
     transition_image_layout(
@@ -355,7 +567,8 @@ pub unsafe fn create_depth_objects(
         format,
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
-        data.texture_data.mip_levels,
+        1,
+        1,
     )?;
 
     Ok(())
@@ -383,23 +596,37 @@ pub unsafe fn create_image_view(
     format: vk::Format,
     aspects: vk::ImageAspectFlags,
     mip_levels: u32,
+    view_type: vk::ImageViewType,
 ) -> Result<vk::ImageView> {
+    let layer_count = if view_type == vk::ImageViewType::CUBE {
+        6
+    } else {
+        1
+    };
+
     let subresource_range = vk::ImageSubresourceRange::builder()
         .aspect_mask(aspects)
         .base_mip_level(0)
         .level_count(mip_levels)
         .base_array_layer(0)
-        .layer_count(1);
+        .layer_count(layer_count);
 
     let info = vk::ImageViewCreateInfo::builder()
         .image(image)
-        .view_type(vk::ImageViewType::_2D)
+        .view_type(view_type)
         .format(format)
         .subresource_range(subresource_range);
 
     Ok(device.create_image_view(&info, None)?)
 }
 
+unsafe fn supports_linear_blit(instance: &Instance, data: &AppData, format: vk::Format) -> bool {
+    instance
+        .get_physical_device_format_properties(data.setup_data.physical_device, format)
+        .optimal_tiling_features
+        .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+}
+
 unsafe fn generate_mipmaps(
     instance: &Instance,
     device: &Device,
@@ -410,11 +637,7 @@ unsafe fn generate_mipmaps(
     height: u32,
     mip_levels: u32,
 ) -> Result<()> {
-    if !instance
-        .get_physical_device_format_properties(data.setup_data.physical_device, format)
-        .optimal_tiling_features
-        .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
-    {
+    if !supports_linear_blit(instance, data, format) {
         return Err(anyhow!(
             "Texture image format does not support linear blitting!"
         ));
@@ -559,6 +782,8 @@ pub unsafe fn create_color_objects(
         data.presentation_data.swapchain_extent.width,
         data.presentation_data.swapchain_extent.height,
         1,
+        1,
+        vk::ImageCreateFlags::empty(),
         data.texture_data.msaa_samples,
         data.presentation_data.swapchain_format,
         vk::ImageTiling::OPTIMAL,
@@ -575,6 +800,7 @@ pub unsafe fn create_color_objects(
         data.presentation_data.swapchain_format,
         vk::ImageAspectFlags::COLOR,
         1,
+        vk::ImageViewType::_2D,
     )?;
 
     Ok(())
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::texture::color_space::ColorSpace;
+use crate::texture::image;
+use crate::AppData;
+
+/// How many textures can be loaded at once.
+pub const MAX_TEXTURES: u32 = 256;
+/// Textures get a descriptor set for each sampler they are used with, which sizes the pool.
+const MAX_DESCRIPTOR_SETS: u32 = MAX_TEXTURES * 4;
+
+/// A texture loaded by the `TextureManager`, valid until its last reference is released.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(u32);
+
+/// A sampled image and the descriptor set that binds it to set 1 with the default sampler.
+#[derive(Clone, Debug, Default)]
+pub struct Texture {
+    pub image: vk::Image,
+    pub image_memory: vk::DeviceMemory,
+    pub image_view: vk::ImageView,
+    pub format: vk::Format,
+    pub color_space: ColorSpace,
+    pub mip_levels: u32,
+    pub descriptor_set: vk::DescriptorSet,
+}
+
+#[derive(Clone, Debug)]
+struct TextureEntry {
+    path: PathBuf,
+    texture: Texture,
+    references: u32,
+    /// Descriptor sets binding the texture with samplers other than the default one.
+    sampler_sets: HashMap<vk::Sampler, vk::DescriptorSet>,
+}
+
+/// Loads each texture file once per color space and frees it once nothing references it.
+#[derive(Clone, Debug, Default)]
+pub struct TextureManager {
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    pub descriptor_pool: vk::DescriptorPool,
+    entries: HashMap<TextureHandle, TextureEntry>,
+    handles: HashMap<(PathBuf, ColorSpace), TextureHandle>,
+    next_handle: u32,
+}
+
+impl TextureManager {
+    pub fn get(&self, handle: TextureHandle) -> Option<&Texture> {
+        self.entries.get(&handle).map(|e| &e.texture)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a reference to the texture loaded from `path` in `color_space`, if there is one.
+    fn acquire(&mut self, path: &Path, color_space: ColorSpace) -> Option<TextureHandle> {
+        let handle = *self.handles.get(&(path.to_path_buf(), color_space))?;
+        self.entries.get_mut(&handle)?.references += 1;
+        Some(handle)
+    }
+
+    fn insert(&mut self, path: PathBuf, texture: Texture) -> TextureHandle {
+        let handle = TextureHandle(self.next_handle);
+        self.next_handle += 1;
+
+        self.handles
+            .insert((path.clone(), texture.color_space), handle);
+        self.entries.insert(
+            handle,
+            TextureEntry {
+                path,
+                texture,
+                references: 1,
+                sampler_sets: HashMap::new(),
+            },
+        );
+
+        handle
+    }
+
+    /// Drops a reference, returning the texture once the last one is gone.
+    fn release(&mut self, handle: TextureHandle) -> Option<TextureEntry> {
+        let entry = self.entries.get_mut(&handle)?;
+        entry.references -= 1;
+        if entry.references > 0 {
+            return None;
+        }
+
+        let entry = self.entries.remove(&handle)?;
+        self.handles
+            .remove(&(entry.path.clone(), entry.texture.color_space));
+        Some(entry)
+    }
+}
+
+/// Creates the layout of the per-texture descriptor sets and the pool they come from.
+pub unsafe fn create_texture_descriptor_objects(device: &Device, data: &mut AppData) -> Result<()> {
+    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
+
+    let bindings = &[sampler_binding];
+    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+
+    let textures = &mut data.texture_data.textures;
+    textures.descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
+
+    let sampler_size = vk::DescriptorPoolSize::builder()
+        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(MAX_DESCRIPTOR_SETS);
+
+    let pool_sizes = &[sampler_size];
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+        .pool_sizes(pool_sizes)
+        .max_sets(MAX_DESCRIPTOR_SETS);
+
+    textures.descriptor_pool = device.create_descriptor_pool(&info, None)?;
+
+    Ok(())
+}
+
+/// Allocates a descriptor set binding `image_view` with `sampler`.
+pub unsafe fn create_texture_descriptor_set(
+    device: &Device,
+    data: &AppData,
+    image_view: vk::ImageView,
+    sampler: vk::Sampler,
+) -> Result<vk::DescriptorSet> {
+    let layouts = &[data.texture_data.textures.descriptor_set_layout];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(data.texture_data.textures.descriptor_pool)
+        .set_layouts(layouts);
+
+    let descriptor_set = device.allocate_descriptor_sets(&info)?[0];
+
+    let info = vk::DescriptorImageInfo::builder()
+        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .image_view(image_view)
+        .sampler(sampler);
+
+    let image_info = &[info];
+    let sampler_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .dst_array_element(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .image_info(image_info);
+
+    device.update_descriptor_sets(&[sampler_write], &[] as &[vk::CopyDescriptorSet]);
+
+    Ok(descriptor_set)
+}
+
+/// Returns the texture loaded from `path`, loading it if nothing references it yet.
+///
+/// The same file loaded as color and as data is two textures, one `_SRGB` and one `_UNORM`.
+/// Every call adds a reference, which `release_texture` drops.
+pub unsafe fn load_texture(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    path: &Path,
+    color_space: ColorSpace,
+) -> Result<TextureHandle> {
+    if let Some(handle) = data.texture_data.textures.acquire(path, color_space) {
+        return Ok(handle);
+    }
+
+    if data.texture_data.textures.len() >= MAX_TEXTURES as usize {
+        return Err(anyhow!(
+            "Too many textures loaded (at most {}).",
+            MAX_TEXTURES
+        ));
+    }
+
+    let (image, image_memory, format, mip_levels) =
+        image::create_texture_image(instance, device, data, path, color_space)?;
+
+    let image_view = match image::create_image_view(
+        device,
+        image,
+        format,
+        vk::ImageAspectFlags::COLOR,
+        mip_levels,
+        vk::ImageViewType::_2D,
+    ) {
+        Ok(image_view) => image_view,
+        Err(e) => {
+            device.destroy_image(image, None);
+            device.free_memory(image_memory, None);
+            return Err(e);
+        }
+    };
+
+    let sampler = data.texture_data.texture_sampler;
+    let descriptor_set = match create_texture_descriptor_set(device, data, image_view, sampler) {
+        Ok(descriptor_set) => descriptor_set,
+        Err(e) => {
+            device.destroy_image_view(image_view, None);
+            device.destroy_image(image, None);
+            device.free_memory(image_memory, None);
+            return Err(e);
+        }
+    };
+
+    let texture = Texture {
+        image,
+        image_memory,
+        image_view,
+        format,
+        color_space,
+        mip_levels,
+        descriptor_set,
+    };
+
+    info!("Loaded texture `{}` ({:?}).", path.display(), color_space);
+    Ok(data
+        .texture_data
+        .textures
+        .insert(path.to_path_buf(), texture))
+}
+
+/// The descriptor set binding a texture with `sampler`, created the first time it's asked for.
+pub unsafe fn get_descriptor_set(
+    device: &Device,
+    data: &mut AppData,
+    handle: TextureHandle,
+    sampler: vk::Sampler,
+) -> Result<vk::DescriptorSet> {
+    let entry = data
+        .texture_data
+        .textures
+        .entries
+        .get(&handle)
+        .ok_or_else(|| anyhow!("Texture {:?} is not loaded.", handle))?;
+
+    if sampler == data.texture_data.texture_sampler {
+        return Ok(entry.texture.descriptor_set);
+    }
+
+    if let Some(descriptor_set) = entry.sampler_sets.get(&sampler) {
+        return Ok(*descriptor_set);
+    }
+
+    let descriptor_set =
+        create_texture_descriptor_set(device, data, entry.texture.image_view, sampler)?;
+
+    data.texture_data
+        .textures
+        .entries
+        .get_mut(&handle)
+        .unwrap()
+        .sampler_sets
+        .insert(sampler, descriptor_set);
+
+    Ok(descriptor_set)
+}
+
+/// Drops a reference to a texture, destroying it once nothing references it.
+///
+/// Frames in flight may still sample the texture, so this waits for all of them first.
+pub unsafe fn release_texture(
+    device: &Device,
+    data: &mut AppData,
+    handle: TextureHandle,
+) -> Result<()> {
+    let Some(entry) = data.texture_data.textures.release(handle) else {
+        return Ok(());
+    };
+
+    let fences = data
+        .drawing_data
+        .frames
+        .iter()
+        .map(|f| f.in_flight_fence)
+        .filter(|f| !f.is_null())
+        .collect::<Vec<_>>();
+    device.wait_for_fences(&fences, true, u64::MAX)?;
+
+    let sampler_sets = entry.sampler_sets.into_values().collect::<Vec<_>>();
+    if !sampler_sets.is_empty() {
+        device.free_descriptor_sets(data.texture_data.textures.descriptor_pool, &sampler_sets)?;
+    }
+
+    destroy_texture(device, data, &entry.texture)?;
+
+    debug!("Texture {:?} destroyed.", handle);
+    Ok(())
+}
+
+/// Destroys a texture the manager doesn't own, or no longer does.
+pub unsafe fn destroy_texture(device: &Device, data: &AppData, texture: &Texture) -> Result<()> {
+    device.free_descriptor_sets(
+        data.texture_data.textures.descriptor_pool,
+        &[texture.descriptor_set],
+    )?;
+
+    device.destroy_image_view(texture.image_view, None);
+    device.destroy_image(texture.image, None);
+    device.free_memory(texture.image_memory, None);
+
+    Ok(())
+}
+
+/// Destroys every texture regardless of references, along with the descriptor objects.
+pub unsafe fn destroy_textures(device: &Device, data: &mut AppData) {
+    let textures = &mut data.texture_data.textures;
+
+    for (_, entry) in textures.entries.drain() {
+        device.destroy_image_view(entry.texture.image_view, None);
+        device.destroy_image(entry.texture.image, None);
+        device.free_memory(entry.texture.image_memory, None);
+    }
+    textures.handles.clear();
+
+    device.destroy_descriptor_pool(textures.descriptor_pool, None);
+    device.destroy_descriptor_set_layout(textures.descriptor_set_layout, None);
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use anyhow::Result;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::texture::color_space::ColorSpace;
+use crate::texture::manager::{self, TextureHandle};
+use crate::texture::sampler::SamplerDesc;
+use crate::AppData;
+
+const DEFAULT_TEXTURE: &str = "/home/mhl/vulkanalia_tutorial/src/texture/resources/viking_room.png";
+
+/// The role a texture plays in a material, which decides its color space.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MaterialSlot {
+    BaseColor,
+    Emissive,
+    Normal,
+    MetallicRoughness,
+    Occlusion,
+    /// Lookup tables and other data that isn't shaded as a color.
+    Data,
+}
+
+impl MaterialSlot {
+    /// Colors are sRGB, everything else is linear data.
+    pub fn color_space(self) -> ColorSpace {
+        match self {
+            Self::BaseColor | Self::Emissive => ColorSpace::Srgb,
+            _ => ColorSpace::Linear,
+        }
+    }
+}
+
+/// What a draw's `material_index` refers to.
+#[derive(Copy, Clone, Debug)]
+pub struct Material {
+    pub texture: TextureHandle,
+    pub sampler: SamplerDesc,
+    /// Binds the texture with the sampler, owned by the texture manager.
+    pub descriptor_set: vk::DescriptorSet,
+}
+
+/// Creates a material sampling `texture` as `sampler` describes.
+pub unsafe fn create_material(
+    device: &Device,
+    data: &mut AppData,
+    texture: TextureHandle,
+    sampler: SamplerDesc,
+) -> Result<Material> {
+    let vk_sampler = data.texture_data.samplers.get(device, &sampler)?;
+    let descriptor_set = manager::get_descriptor_set(device, data, texture, vk_sampler)?;
+
+    Ok(Material {
+        texture,
+        sampler,
+        descriptor_set,
+    })
+}
+
+pub unsafe fn create_materials(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let path = Path::new(DEFAULT_TEXTURE);
+    let color_space = MaterialSlot::BaseColor.color_space();
+    let texture = manager::load_texture(instance, device, data, path, color_space)?;
+    let material = create_material(device, data, texture, data.config.sampler)?;
+    data.texture_data.materials.push(material);
+
+    Ok(())
+}
+
+/// Drops the material's reference to its texture, destroying the texture if it was the last.
+pub unsafe fn release_material(
+    device: &Device,
+    data: &mut AppData,
+    material: Material,
+) -> Result<()> {
+    manager::release_texture(device, data, material.texture)
+}
+
+/// Releases every material, which frees their textures.
+pub unsafe fn destroy_materials(device: &Device, data: &mut AppData) -> Result<()> {
+    let materials = std::mem::take(&mut data.texture_data.materials);
+    for material in materials {
+        release_material(device, data, material)?;
+    }
+
+    Ok(())
+}
+
+/// The descriptor set binding a material's texture, falling back to the first material.
+pub fn get_material_descriptor_set(data: &AppData, material_index: u32) -> vk::DescriptorSet {
+    let materials = &data.texture_data.materials;
+
+    materials
+        .get(material_index as usize)
+        .or(materials.first())
+        .map_or(vk::DescriptorSet::null(), |m| m.descriptor_set)
+}
@@ -0,0 +1,269 @@
+use std::f32::consts::PI;
+
+use anyhow::{anyhow, Result};
+
+/// How the mip chain of textures without pre-baked mips is generated.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MipmapFilter {
+    /// Linear blits on the GPU, which filter sRGB data without linearizing it.
+    #[default]
+    Blit,
+    /// A gamma-correct 2x2 box filter on the CPU.
+    Box,
+    /// A gamma-correct Kaiser-windowed sinc filter on the CPU, which keeps more detail.
+    Kaiser,
+}
+
+impl MipmapFilter {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "blit" => Ok(Self::Blit),
+            "box" => Ok(Self::Box),
+            "kaiser" => Ok(Self::Kaiser),
+            _ => Err(anyhow!(
+                "Unknown mipmap filter `{}` (expected `blit`, `box` or `kaiser`).",
+                value
+            )),
+        }
+    }
+
+    /// Source texel offsets from `2 * x` and their weights, for destination texel `x`.
+    fn taps(self) -> Vec<(i32, f32)> {
+        match self {
+            Self::Blit | Self::Box => vec![(0, 0.5), (1, 0.5)],
+            Self::Kaiser => {
+                // Each tap's distance from the destination texel center, in destination texels.
+                let taps = (-2..=3)
+                    .map(|o| (o, kaiser((o as f32 - 0.5) / 2.0)))
+                    .collect::<Vec<_>>();
+                let sum = taps.iter().map(|(_, w)| w).sum::<f32>();
+                taps.into_iter().map(|(o, w)| (o, w / sum)).collect()
+            }
+        }
+    }
+}
+
+const KAISER_WIDTH: f32 = 3.0;
+const KAISER_ALPHA: f32 = 4.0;
+
+fn kaiser(x: f32) -> f32 {
+    let sinc = if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    };
+
+    let t = x / KAISER_WIDTH;
+    let window = bessel_i0(KAISER_ALPHA * (1.0 - t * t).max(0.0).sqrt()) / bessel_i0(KAISER_ALPHA);
+
+    sinc * window
+}
+
+/// The zeroth order modified Bessel function of the first kind.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..20 {
+        term *= (x / (2.0 * k as f32)).powi(2);
+        sum += term;
+    }
+    sum
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let c = value.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// RGBA8 pixels as linear texels; only color channels are sRGB encoded, alpha is linear.
+pub fn decode_rgba8(pixels: &[u8], srgb: bool) -> Vec<[f32; 4]> {
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|v| {
+            if srgb {
+                srgb_to_linear(v)
+            } else {
+                v as f32 / 255.0
+            }
+        })
+        .collect();
+
+    pixels
+        .chunks_exact(4)
+        .map(|p| {
+            [
+                to_linear[p[0] as usize],
+                to_linear[p[1] as usize],
+                to_linear[p[2] as usize],
+                p[3] as f32 / 255.0,
+            ]
+        })
+        .collect()
+}
+
+/// Linear texels as RGBA8 pixels, the inverse of `decode_rgba8`.
+pub fn encode_rgba8(texels: &[[f32; 4]], srgb: bool) -> Vec<u8> {
+    let unorm = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let color = |c: f32| if srgb { linear_to_srgb(c) } else { unorm(c) };
+
+    texels
+        .iter()
+        .flat_map(|t| [color(t[0]), color(t[1]), color(t[2]), unorm(t[3])])
+        .collect()
+}
+
+/// Builds the mip chain of linear texels, the full-size level first.
+pub fn generate_chain(
+    texels: Vec<[f32; 4]>,
+    width: u32,
+    height: u32,
+    filter: MipmapFilter,
+) -> Vec<Vec<[f32; 4]>> {
+    let taps = filter.taps();
+    let (mut width, mut height) = (width, height);
+    let mut levels = vec![texels];
+
+    while width > 1 || height > 1 {
+        let image = downsample(levels.last().unwrap(), width, height, &taps, true);
+        width = (width / 2).max(1);
+        let image = downsample(&image, height, width, &taps, false);
+        height = (height / 2).max(1);
+        levels.push(image);
+    }
+
+    levels
+}
+
+/// Builds the mip chain of RGBA8 `pixels` on the CPU, the full-size level first.
+///
+/// Color channels of sRGB data are filtered in linear space; alpha always is linear.
+pub fn generate_mipmaps(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    srgb: bool,
+    filter: MipmapFilter,
+) -> Vec<Vec<u8>> {
+    let texels = decode_rgba8(pixels, srgb);
+    let mut levels = generate_chain(texels, width, height, filter)
+        .iter()
+        .map(|l| encode_rgba8(l, srgb))
+        .collect::<Vec<_>>();
+
+    // The full-size level is kept as is rather than round-tripped.
+    levels[0] = pixels.to_vec();
+    levels
+}
+
+/// Halves `image` along one axis, rows if `horizontal` and columns otherwise.
+///
+/// `length` is the size along that axis and `other` the size along the other one.
+fn downsample(
+    image: &[[f32; 4]],
+    length: u32,
+    other: u32,
+    taps: &[(i32, f32)],
+    horizontal: bool,
+) -> Vec<[f32; 4]> {
+    if length == 1 {
+        return image.to_vec();
+    }
+
+    let half = length / 2;
+    let index = |along: u32, across: u32| {
+        if horizontal {
+            (across * length + along) as usize
+        } else {
+            (along * other + across) as usize
+        }
+    };
+
+    let mut result = vec![[0.0; 4]; (half * other) as usize];
+    for across in 0..other {
+        for x in 0..half {
+            let mut texel = [0.0; 4];
+            for (offset, weight) in taps {
+                let source = (2 * x as i32 + offset).clamp(0, length as i32 - 1) as u32;
+                let source = image[index(source, across)];
+                for (t, s) in texel.iter_mut().zip(source) {
+                    *t += s * weight;
+                }
+            }
+
+            let target = if horizontal {
+                (across * half + x) as usize
+            } else {
+                (x * other + across) as usize
+            };
+            result[target] = texel;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_reaches_1x1() {
+        for (width, height) in [(1, 1), (7, 3), (5, 5), (1, 9), (640, 480)] {
+            let texels = vec![[0.0; 4]; (width * height) as usize];
+            let levels = generate_chain(texels, width, height, MipmapFilter::Box);
+
+            let expected = width.max(height).ilog2() + 1;
+            assert_eq!(levels.len(), expected as usize, "{}x{}", width, height);
+            assert_eq!(levels.last().unwrap().len(), 1, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn test_level_sizes() {
+        let levels = generate_chain(vec![[0.0; 4]; 7 * 3], 7, 3, MipmapFilter::Kaiser);
+        let sizes = levels.iter().map(|l| l.len()).collect::<Vec<_>>();
+        assert_eq!(sizes, [7 * 3, 3, 1]);
+    }
+
+    #[test]
+    fn test_tap_weights_sum_to_one() {
+        for filter in [MipmapFilter::Box, MipmapFilter::Kaiser] {
+            let sum = filter.taps().iter().map(|(_, w)| w).sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-6, "{:?}: {}", filter, sum);
+        }
+    }
+
+    #[test]
+    fn test_gamma_correct_average() {
+        let black = [0, 0, 0, 255];
+        let white = [255, 255, 255, 255];
+        let pixels = [black, white, white, black].concat();
+
+        for filter in [MipmapFilter::Box, MipmapFilter::Kaiser] {
+            let levels = generate_mipmaps(&pixels, 2, 2, true, filter);
+            assert_eq!(levels.len(), 2);
+
+            // Linear 0.5 is 188 in sRGB, where averaging the encoded values would give 128.
+            let texel = &levels[1];
+            assert!(
+                texel[..3].iter().all(|c| c.abs_diff(188) <= 1),
+                "{:?}",
+                texel
+            );
+            assert_eq!(texel[3], 255);
+        }
+    }
+}
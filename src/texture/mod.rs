@@ -1,16 +1,30 @@
 use vulkanalia::prelude::v1_0::*;
 
+use self::manager::{Texture, TextureManager};
+use self::material::Material;
+use self::sampler::SamplerCache;
+
+pub mod color_space;
+pub mod container;
+pub mod cubemap;
+pub mod decode;
 pub mod image;
+pub mod manager;
+pub mod material;
+pub mod mipmap;
 pub mod multisampling;
+pub mod sampler;
 
 #[derive(Clone, Debug, Default)]
 pub struct TextureData {
-    pub mip_levels: u32,
     pub msaa_samples: vk::SampleCountFlags,
-    pub texture_image: vk::Image,
-    pub texture_image_memory: vk::DeviceMemory,
-    pub texture_image_view: vk::ImageView,
+    pub textures: TextureManager,
+    pub materials: Vec<Material>,
+    pub samplers: SamplerCache,
+    /// The default sampler, owned by `samplers`.
     pub texture_sampler: vk::Sampler,
+    /// The cubemap drawn behind the scene, if one was given with `--skybox`.
+    pub skybox: Option<Texture>,
     pub depth_image: vk::Image,
     pub depth_image_memory: vk::DeviceMemory,
     pub depth_image_view: vk::ImageView,
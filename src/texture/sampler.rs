@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use vulkanalia::prelude::v1_0::*;
+
+use crate::AppData;
+
+/// A `SamplerDesc` with its floats as bits, used to deduplicate samplers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerKey {
+    mag_filter: vk::Filter,
+    min_filter: vk::Filter,
+    mipmap_mode: vk::SamplerMipmapMode,
+    address_mode_u: vk::SamplerAddressMode,
+    address_mode_v: vk::SamplerAddressMode,
+    address_mode_w: vk::SamplerAddressMode,
+    border_color: vk::BorderColor,
+    max_anisotropy: Option<u32>,
+    mip_lod_bias: u32,
+    min_lod: u32,
+    max_lod: u32,
+    compare_op: Option<vk::CompareOp>,
+}
+
+/// Everything needed to build a sampler.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SamplerDesc {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode_u: vk::SamplerAddressMode,
+    pub address_mode_v: vk::SamplerAddressMode,
+    pub address_mode_w: vk::SamplerAddressMode,
+    pub border_color: vk::BorderColor,
+    /// Anisotropic filtering, clamped to the device's limit, or `None` to disable it.
+    pub max_anisotropy: Option<f32>,
+    pub mip_lod_bias: f32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    /// Compares against a reference value instead of filtering, for depth textures.
+    pub compare_op: Option<vk::CompareOp>,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode_u: vk::SamplerAddressMode::REPEAT,
+            address_mode_v: vk::SamplerAddressMode::REPEAT,
+            address_mode_w: vk::SamplerAddressMode::REPEAT,
+            border_color: vk::BorderColor::INT_OPAQUE_BLACK,
+            max_anisotropy: Some(16.0),
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            compare_op: None,
+        }
+    }
+}
+
+impl SamplerDesc {
+    /// Parses one of the presets.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "default" => Ok(Self::default()),
+            "pixel-art" => Ok(Self::pixel_art()),
+            "ui" => Ok(Self::ui()),
+            _ => Err(anyhow!(
+                "Unknown sampler `{}` (expected `default`, `pixel-art` or `ui`).",
+                value
+            )),
+        }
+    }
+
+    /// Sharp texels up close, still mipmapped in the distance.
+    pub fn pixel_art() -> Self {
+        Self {
+            mag_filter: vk::Filter::NEAREST,
+            min_filter: vk::Filter::NEAREST,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            max_anisotropy: None,
+            ..Default::default()
+        }
+    }
+
+    /// No wrapping at the edges, and no mips since UI is drawn at its own size.
+    pub fn ui() -> Self {
+        Self {
+            address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            max_anisotropy: None,
+            max_lod: 0.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn key(&self) -> SamplerKey {
+        SamplerKey {
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_mode: self.mipmap_mode,
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: self.address_mode_w,
+            border_color: self.border_color,
+            max_anisotropy: self.max_anisotropy.map(f32::to_bits),
+            mip_lod_bias: self.mip_lod_bias.to_bits(),
+            min_lod: self.min_lod.to_bits(),
+            max_lod: self.max_lod.to_bits(),
+            compare_op: self.compare_op,
+        }
+    }
+}
+
+/// Creates samplers on demand and hands out one sampler per unique `SamplerDesc`.
+#[derive(Clone, Debug, Default)]
+pub struct SamplerCache {
+    /// The device's `max_sampler_anisotropy`.
+    pub max_anisotropy: f32,
+    samplers: HashMap<SamplerKey, vk::Sampler>,
+}
+
+impl SamplerCache {
+    pub unsafe fn get(&mut self, device: &Device, desc: &SamplerDesc) -> Result<vk::Sampler> {
+        let key = desc.key();
+
+        if let Some(sampler) = self.samplers.get(&key) {
+            return Ok(*sampler);
+        }
+
+        let anisotropy = desc
+            .max_anisotropy
+            .map(|a| a.clamp(1.0, self.max_anisotropy.max(1.0)));
+
+        let info = vk::SamplerCreateInfo::builder()
+            .mag_filter(desc.mag_filter)
+            .min_filter(desc.min_filter)
+            .address_mode_u(desc.address_mode_u)
+            .address_mode_v(desc.address_mode_v)
+            .address_mode_w(desc.address_mode_w)
+            .anisotropy_enable(anisotropy.is_some())
+            .max_anisotropy(anisotropy.unwrap_or(1.0))
+            .border_color(desc.border_color)
+            .unnormalized_coordinates(false)
+            .compare_enable(desc.compare_op.is_some())
+            .compare_op(desc.compare_op.unwrap_or(vk::CompareOp::ALWAYS))
+            .mipmap_mode(desc.mipmap_mode)
+            .min_lod(desc.min_lod)
+            .max_lod(desc.max_lod)
+            .mip_lod_bias(desc.mip_lod_bias);
+
+        let sampler = device.create_sampler(&info, None)?;
+        self.samplers.insert(key, sampler);
+
+        debug!("Sampler created ({} cached).", self.samplers.len());
+        Ok(sampler)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+
+    pub unsafe fn destroy(&mut self, device: &Device) {
+        self.samplers
+            .drain()
+            .for_each(|(_, s)| device.destroy_sampler(s, None));
+    }
+}
+
+/// Creates the sampler textures are bound with unless their material asks for another one.
+pub unsafe fn create_texture_sampler(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    let properties = instance.get_physical_device_properties(data.setup_data.physical_device);
+    data.texture_data.samplers.max_anisotropy = properties.limits.max_sampler_anisotropy;
+
+    data.texture_data.texture_sampler = data
+        .texture_data
+        .samplers
+        .get(device, &SamplerDesc::default())?;
+
+    Ok(())
+}
@@ -2,9 +2,11 @@ use std::mem::size_of;
 use std::ptr::copy_nonoverlapping as memcpy;
 
 use anyhow::Result;
-use cgmath::{point3, vec3, Deg};
+use cgmath::SquareMatrix;
 use vulkanalia::prelude::v1_0::*;
 
+use crate::drawing::viewport::MAX_VIEWPORTS;
+use crate::scene::camera::Camera;
 use crate::vertex::vertex;
 use crate::App;
 use crate::AppData;
@@ -13,26 +15,27 @@ type Mat4 = cgmath::Matrix4<f32>;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
-struct UniformBufferObject {
-    model: Mat4,
+struct ViewProj {
     view: Mat4,
     proj: Mat4,
 }
 
+/// The view and projection of each viewport, indexed by the `viewport_index` push constant.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct UniformBufferObject {
+    viewports: [ViewProj; MAX_VIEWPORTS],
+}
+
 pub unsafe fn create_descriptor_set_layout(device: &Device, data: &mut AppData) -> Result<()> {
     let ubo_binding = vk::DescriptorSetLayoutBinding::builder()
         .binding(0)
         .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::VERTEX);
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT);
 
-    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
-        .binding(1)
-        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
-
-    let bindings = &[ubo_binding, sampler_binding];
+    // Textures are bound per material, with the sets of the texture manager.
+    let bindings = &[ubo_binding];
     let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
 
     data.uniform_data.descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
@@ -45,10 +48,7 @@ pub unsafe fn create_uniform_buffers(
     device: &Device,
     data: &mut AppData,
 ) -> Result<()> {
-    data.uniform_data.uniform_buffers.clear();
-    data.uniform_data.uniform_buffers_memory.clear();
-
-    for _ in 0..data.presentation_data.swapchain_images.len() {
+    for i in 0..data.drawing_data.frames.len() {
         let (uniform_buffer, uniform_buffer_memory) = vertex::create_buffer(
             instance,
             device,
@@ -58,25 +58,15 @@ pub unsafe fn create_uniform_buffers(
             vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
         )?;
 
-        data.uniform_data.uniform_buffers.push(uniform_buffer);
-        data.uniform_data
-            .uniform_buffers_memory
-            .push(uniform_buffer_memory);
+        data.drawing_data.frames[i].uniform_buffer = uniform_buffer;
+        data.drawing_data.frames[i].uniform_buffer_memory = uniform_buffer_memory;
     }
 
     Ok(())
 }
 
-pub unsafe fn update_uniform_buffer(app: &App, image_index: usize) -> Result<()> {
-    let time = app.start.elapsed().as_secs_f32();
-
-    let model = Mat4::from_axis_angle(vec3(0.0, 0.0, 1.0), Deg(90.0) * time);
-
-    let view = Mat4::look_at_rh(
-        point3(2.0, 2.0, 2.0),
-        point3(0.0, 0.0, 0.0),
-        vec3(0.0, 0.0, 1.0),
-    );
+pub fn get_view_proj(camera: &Camera, extent: vk::Extent2D) -> (Mat4, Mat4) {
+    let view = camera.view();
 
     let correction = Mat4::new(
         1.0,
@@ -98,19 +88,37 @@ pub unsafe fn update_uniform_buffer(app: &App, image_index: usize) -> Result<()>
         1.0,
     );
 
-    let proj = correction
-        * cgmath::perspective(
-            Deg(45.0),
-            app.data.presentation_data.swapchain_extent.width as f32
-                / app.data.presentation_data.swapchain_extent.height as f32,
-            0.1,
-            10.0,
-        );
+    let aspect = extent.width as f32 / extent.height as f32;
+    let projection = match camera.orthographic {
+        Some(height) => {
+            let (x, y) = (height * aspect / 2.0, height / 2.0);
+            cgmath::ortho(-x, x, -y, y, camera.near, camera.far)
+        }
+        None => cgmath::perspective(camera.fov, aspect, camera.near, camera.far),
+    };
+
+    let proj = correction * projection;
+
+    (view, proj)
+}
 
-    let ubo = UniformBufferObject { model, view, proj };
+pub unsafe fn update_uniform_buffer(app: &App, frame: usize) -> Result<()> {
+    let identity = ViewProj {
+        view: Mat4::identity(),
+        proj: Mat4::identity(),
+    };
+    let mut ubo = UniformBufferObject {
+        viewports: [identity; MAX_VIEWPORTS],
+    };
+
+    for (i, viewport) in app.data.drawing_data.viewports.iter().enumerate() {
+        let (view, proj) = get_view_proj(&viewport.camera, viewport.rect.extent);
+        ubo.viewports[i] = ViewProj { view, proj };
+    }
 
+    let uniform_buffer_memory = app.data.drawing_data.frames[frame].uniform_buffer_memory;
     let memory = app.device.map_memory(
-        app.data.uniform_data.uniform_buffers_memory[image_index],
+        uniform_buffer_memory,
         0,
         size_of::<UniformBufferObject>() as u64,
         vk::MemoryMapFlags::empty(),
@@ -118,25 +126,24 @@ pub unsafe fn update_uniform_buffer(app: &App, image_index: usize) -> Result<()>
 
     memcpy(&ubo, memory.cast(), 1);
 
-    app.device
-        .unmap_memory(app.data.uniform_data.uniform_buffers_memory[image_index]);
+    app.device.unmap_memory(uniform_buffer_memory);
 
     Ok(())
 }
 
 pub unsafe fn create_descriptor_pool(device: &Device, data: &mut AppData) -> Result<()> {
+    let frame_count = data.drawing_data.frames.len() as u32;
+
     let ubo_size = vk::DescriptorPoolSize::builder()
         .type_(vk::DescriptorType::UNIFORM_BUFFER)
-        .descriptor_count(data.presentation_data.swapchain_images.len() as u32);
+        .descriptor_count(frame_count);
 
-    let sampler_size = vk::DescriptorPoolSize::builder()
-        .type_(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(data.presentation_data.swapchain_images.len() as u32);
-
-    let pool_sizes = &[ubo_size, sampler_size];
+    let pool_sizes = &[ubo_size];
+    // The sets of a closed window's frames are freed with it.
     let info = vk::DescriptorPoolCreateInfo::builder()
+        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
         .pool_sizes(pool_sizes)
-        .max_sets(data.presentation_data.swapchain_images.len() as u32);
+        .max_sets(frame_count);
 
     data.uniform_data.descriptor_pool = device.create_descriptor_pool(&info, None)?;
 
@@ -144,44 +151,30 @@ pub unsafe fn create_descriptor_pool(device: &Device, data: &mut AppData) -> Res
 }
 
 pub unsafe fn create_descriptor_sets(device: &Device, data: &mut AppData) -> Result<()> {
-    let layouts = vec![
-        data.uniform_data.descriptor_set_layout;
-        data.presentation_data.swapchain_images.len()
-    ];
+    let layouts = vec![data.uniform_data.descriptor_set_layout; data.drawing_data.frames.len()];
     let info = vk::DescriptorSetAllocateInfo::builder()
         .descriptor_pool(data.uniform_data.descriptor_pool)
         .set_layouts(&layouts);
 
-    data.uniform_data.descriptor_sets = device.allocate_descriptor_sets(&info)?;
+    let descriptor_sets = device.allocate_descriptor_sets(&info)?;
+
+    for (frame, descriptor_set) in data.drawing_data.frames.iter_mut().zip(descriptor_sets) {
+        frame.descriptor_set = descriptor_set;
 
-    for i in 0..data.presentation_data.swapchain_images.len() {
         let info = vk::DescriptorBufferInfo::builder()
-            .buffer(data.uniform_data.uniform_buffers[i])
+            .buffer(frame.uniform_buffer)
             .offset(0)
             .range(size_of::<UniformBufferObject>() as u64);
 
         let buffer_info = &[info];
         let ubo_write = vk::WriteDescriptorSet::builder()
-            .dst_set(data.uniform_data.descriptor_sets[i])
+            .dst_set(descriptor_set)
             .dst_binding(0)
             .dst_array_element(0)
             .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
             .buffer_info(buffer_info);
 
-        let info = vk::DescriptorImageInfo::builder()
-            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-            .image_view(data.texture_data.texture_image_view)
-            .sampler(data.texture_data.texture_sampler);
-
-        let image_info = &[info];
-        let sampler_write = vk::WriteDescriptorSet::builder()
-            .dst_set(data.uniform_data.descriptor_sets[i])
-            .dst_binding(1)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-            .image_info(image_info);
-
-        device.update_descriptor_sets(&[ubo_write, sampler_write], &[] as &[vk::CopyDescriptorSet]);
+        device.update_descriptor_sets(&[ubo_write], &[] as &[vk::CopyDescriptorSet]);
     }
 
     Ok(())
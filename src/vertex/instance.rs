@@ -0,0 +1,144 @@
+use std::mem::size_of;
+use std::ptr::copy_nonoverlapping as memcpy;
+
+use anyhow::Result;
+use cgmath::{vec4, SquareMatrix};
+use vulkanalia::prelude::v1_0::*;
+
+use crate::vertex::vertex;
+use crate::AppData;
+
+type Vec4 = cgmath::Vector4<f32>;
+type Mat4 = cgmath::Matrix4<f32>;
+
+const INITIAL_INSTANCE_CAPACITY: usize = 1024;
+
+/// Per-instance vertex data, read at `VertexInputRate::INSTANCE` from binding 1.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct InstanceData {
+    pub model: Mat4,
+    pub tint: Vec4,
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        Self::new(Mat4::identity())
+    }
+}
+
+impl InstanceData {
+    pub fn new(model: Mat4) -> Self {
+        Self {
+            model,
+            tint: vec4(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn binding_description() -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(1)
+            .stride(size_of::<InstanceData>() as u32)
+            .input_rate(vk::VertexInputRate::INSTANCE)
+            .build()
+    }
+
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 5] {
+        // A `mat4` attribute takes up one location per column.
+        let column = |i: u32| {
+            vk::VertexInputAttributeDescription::builder()
+                .binding(1)
+                .location(4 + i)
+                .format(vk::Format::R32G32B32A32_SFLOAT)
+                .offset(i * size_of::<Vec4>() as u32)
+                .build()
+        };
+        let tint = vk::VertexInputAttributeDescription::builder()
+            .binding(1)
+            .location(8)
+            .format(vk::Format::R32G32B32A32_SFLOAT)
+            .offset(size_of::<Mat4>() as u32)
+            .build();
+        [column(0), column(1), column(2), column(3), tint]
+    }
+}
+
+pub unsafe fn create_instance_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    data.vertex_data.instance_buffers.clear();
+    data.vertex_data.instance_buffers_memory.clear();
+    data.vertex_data.instance_capacities.clear();
+
+    for _ in 0..data.drawing_data.frames.len() {
+        let (instance_buffer, instance_buffer_memory) =
+            create_instance_buffer(instance, device, data, INITIAL_INSTANCE_CAPACITY)?;
+
+        data.vertex_data.instance_buffers.push(instance_buffer);
+        data.vertex_data
+            .instance_buffers_memory
+            .push(instance_buffer_memory);
+        data.vertex_data
+            .instance_capacities
+            .push(INITIAL_INSTANCE_CAPACITY);
+    }
+
+    Ok(())
+}
+
+unsafe fn create_instance_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    capacity: usize,
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    vertex::create_buffer(
+        instance,
+        device,
+        data,
+        (size_of::<InstanceData>() * capacity) as u64,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )
+}
+
+/// Uploads the instances of this frame's draw list, growing the buffer if needed.
+pub unsafe fn update_instance_buffer(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    frame: usize,
+) -> Result<()> {
+    let count = data.drawing_data.draw_list.instances.len();
+
+    if count > data.vertex_data.instance_capacities[frame] {
+        let capacity = count.next_power_of_two();
+        let (instance_buffer, instance_buffer_memory) =
+            create_instance_buffer(instance, device, data, capacity)?;
+
+        device.destroy_buffer(data.vertex_data.instance_buffers[frame], None);
+        device.free_memory(data.vertex_data.instance_buffers_memory[frame], None);
+
+        data.vertex_data.instance_buffers[frame] = instance_buffer;
+        data.vertex_data.instance_buffers_memory[frame] = instance_buffer_memory;
+        data.vertex_data.instance_capacities[frame] = capacity;
+    }
+
+    let instances = &data.drawing_data.draw_list.instances;
+    if instances.is_empty() {
+        return Ok(());
+    }
+
+    let memory = data.vertex_data.instance_buffers_memory[frame];
+    let size = (size_of::<InstanceData>() * instances.len()) as u64;
+
+    let destination = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+
+    memcpy(instances.as_ptr(), destination.cast(), instances.len());
+
+    device.unmap_memory(memory);
+
+    Ok(())
+}
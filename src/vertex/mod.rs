@@ -2,6 +2,7 @@ use vulkanalia::prelude::v1_0::*;
 
 use self::vertex::Vertex;
 
+pub mod instance;
 pub mod vertex;
 
 #[derive(Clone, Debug, Default)]
@@ -13,4 +14,9 @@ pub struct VertexData {
     pub vertex_buffer_memory: vk::DeviceMemory,
     pub index_buffer: vk::Buffer,
     pub index_buffer_memory: vk::DeviceMemory,
+    pub bounds_center: [f32; 3],
+    pub bounds_radius: f32,
+    pub instance_buffers: Vec<vk::Buffer>,
+    pub instance_buffers_memory: Vec<vk::DeviceMemory>,
+    pub instance_capacities: Vec<usize>,
 }
@@ -0,0 +1,77 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+// 1: normals, 2: UV checker, 3: mip level, 4: depth, 5: vertex color.
+layout(constant_id = 0) const int VIEW_MODE = 1;
+
+const int MAX_VIEWPORTS = 4;
+
+struct ViewProj {
+    mat4 view;
+    mat4 proj;
+};
+
+layout(binding = 0) uniform UniformBufferObject {
+    ViewProj viewports[MAX_VIEWPORTS];
+} ubo;
+
+layout(push_constant) uniform PushConstants {
+    mat4 model;
+    uint materialIndex;
+    uint viewportIndex;
+} pcs;
+
+layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragTexCoord;
+layout(location = 2) in vec3 fragNormal;
+layout(location = 3) in vec3 fragPosition;
+layout(set = 1, binding = 0) uniform sampler2D texSampler;
+
+layout(location = 0) out vec4 outColor;
+
+#include "encode.glsl"
+
+const vec3 MIP_COLORS[8] = vec3[](
+    vec3(1.0, 0.0, 0.0),
+    vec3(1.0, 0.5, 0.0),
+    vec3(1.0, 1.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 1.0, 1.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.5, 0.0, 1.0),
+    vec3(1.0, 0.0, 1.0)
+);
+
+// The view-space distance of a depth buffer value, for perspective and orthographic
+// projections alike.
+float viewDepth(mat4 inverseProj, float depth) {
+    vec4 position = inverseProj * vec4(0.0, 0.0, depth, 1.0);
+    return -position.z / position.w;
+}
+
+void main() {
+    if (VIEW_MODE == 1) {
+        // Fall back to the face normal when the mesh has no normals.
+        vec3 normal = length(fragNormal) > 0.0
+            ? normalize(fragNormal)
+            : normalize(cross(dFdx(fragPosition), dFdy(fragPosition)));
+        outColor = vec4(normal * 0.5 + 0.5, 1.0);
+    } else if (VIEW_MODE == 2) {
+        vec2 cell = floor(fragTexCoord * 16.0);
+        float checker = mod(cell.x + cell.y, 2.0);
+        outColor = vec4(mix(vec3(0.1), vec3(0.9), checker) * vec3(fract(fragTexCoord), 1.0), 1.0);
+    } else if (VIEW_MODE == 3) {
+        float lod = textureQueryLod(texSampler, fragTexCoord).x;
+        int level = clamp(int(floor(lod)), 0, 6);
+        outColor = vec4(mix(MIP_COLORS[level], MIP_COLORS[level + 1], fract(lod)), 1.0);
+    } else if (VIEW_MODE == 4) {
+        mat4 inverseProj = inverse(ubo.viewports[pcs.viewportIndex].proj);
+        float far = viewDepth(inverseProj, 1.0);
+        float depth = viewDepth(inverseProj, gl_FragCoord.z);
+        outColor = vec4(vec3(1.0 - depth / far), 1.0);
+    } else {
+        outColor = vec4(fragColor, 1.0);
+    }
+
+    outColor.rgb = encodeOutput(outColor.rgb);
+}
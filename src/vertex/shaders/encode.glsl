@@ -0,0 +1,42 @@
+// Shared by the fragment shaders that write to the swapchain, included with
+// GL_GOOGLE_include_directive.
+
+// 0: written as is, 1: sRGB, 2: HDR10 (BT.2020 primaries, PQ), 3: scRGB (linear, 1.0 = 80 nits).
+layout(constant_id = 1) const int OUTPUT_ENCODING = 0;
+
+// The brightness SDR white is mapped to on HDR displays.
+const float PAPER_WHITE_NITS = 203.0;
+
+const mat3 REC709_TO_REC2020 = mat3(
+    0.6274, 0.0691, 0.0164,
+    0.3293, 0.9195, 0.0880,
+    0.0433, 0.0114, 0.8956
+);
+
+vec3 encodeSrgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, step(vec3(0.0031308), color));
+}
+
+vec3 encodePq(vec3 nits) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+    vec3 y = pow(clamp(nits / 10000.0, 0.0, 1.0), vec3(m1));
+    return pow((c1 + c2 * y) / (1.0 + c3 * y), vec3(m2));
+}
+
+// Encodes linear Rec.709 color for the swapchain's format and color space.
+vec3 encodeOutput(vec3 color) {
+    if (OUTPUT_ENCODING == 1) {
+        return encodeSrgb(clamp(color, 0.0, 1.0));
+    } else if (OUTPUT_ENCODING == 2) {
+        return encodePq(REC709_TO_REC2020 * color * PAPER_WHITE_NITS);
+    } else if (OUTPUT_ENCODING == 3) {
+        return color * (PAPER_WHITE_NITS / 80.0);
+    }
+    return color;
+}
@@ -1,20 +1,43 @@
 #version 450
 
-layout(binding = 0) uniform UniformBufferObject {
-    mat4 model;
+const int MAX_VIEWPORTS = 4;
+
+struct ViewProj {
     mat4 view;
     mat4 proj;
+};
+
+layout(binding = 0) uniform UniformBufferObject {
+    ViewProj viewports[MAX_VIEWPORTS];
 } ubo;
 
+layout(push_constant) uniform PushConstants {
+    mat4 model;
+    uint materialIndex;
+    uint viewportIndex;
+} pcs;
+
 layout(location = 0) in vec3 inPosition;
 layout(location = 1) in vec3 inColor;
 layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in vec3 inNormal;
+layout(location = 4) in mat4 inInstanceModel;
+layout(location = 8) in vec4 inInstanceTint;
 
 layout(location = 0) out vec3 fragColor;
 layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec3 fragNormal;
+layout(location = 3) out vec3 fragPosition;
+layout(location = 4) out vec4 fragTint;
 
 void main() {
-    gl_Position = ubo.proj * ubo.view * ubo.model * vec4(inPosition, 1.0);
+    mat4 model = pcs.model * inInstanceModel;
+    vec4 position = model * vec4(inPosition, 1.0);
+    ViewProj viewProj = ubo.viewports[pcs.viewportIndex];
+    gl_Position = viewProj.proj * viewProj.view * position;
     fragColor = inColor;
     fragTexCoord = inTexCoord;
+    fragNormal = mat3(model) * inNormal;
+    fragPosition = position.xyz;
+    fragTint = inInstanceTint;
 }
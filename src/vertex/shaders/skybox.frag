@@ -0,0 +1,16 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 fragDirection;
+layout(set = 1, binding = 0) uniform samplerCube skybox;
+
+layout(location = 0) out vec4 outColor;
+
+#include "encode.glsl"
+
+void main() {
+    // The scene is Z-up while cubemaps are Y-up.
+    vec3 direction = vec3(fragDirection.x, fragDirection.z, -fragDirection.y);
+    vec3 color = texture(skybox, direction).rgb;
+    outColor = vec4(encodeOutput(color), 1.0);
+}
@@ -0,0 +1,54 @@
+#version 450
+
+const int MAX_VIEWPORTS = 4;
+
+struct ViewProj {
+    mat4 view;
+    mat4 proj;
+};
+
+layout(binding = 0) uniform UniformBufferObject {
+    ViewProj viewports[MAX_VIEWPORTS];
+} ubo;
+
+layout(push_constant) uniform PushConstants {
+    mat4 model;
+    uint materialIndex;
+    uint viewportIndex;
+} pcs;
+
+layout(location = 0) out vec3 fragDirection;
+
+// The skybox cube is drawn without vertex buffers, as 12 triangles of these corners.
+const vec3 CORNERS[8] = vec3[](
+    vec3(-1.0, -1.0, -1.0),
+    vec3( 1.0, -1.0, -1.0),
+    vec3(-1.0,  1.0, -1.0),
+    vec3( 1.0,  1.0, -1.0),
+    vec3(-1.0, -1.0,  1.0),
+    vec3( 1.0, -1.0,  1.0),
+    vec3(-1.0,  1.0,  1.0),
+    vec3( 1.0,  1.0,  1.0)
+);
+
+const int INDICES[36] = int[](
+    0, 1, 3, 0, 3, 2,
+    4, 6, 7, 4, 7, 5,
+    0, 4, 5, 0, 5, 1,
+    2, 3, 7, 2, 7, 6,
+    0, 2, 6, 0, 6, 4,
+    1, 5, 7, 1, 7, 3
+);
+
+void main() {
+    vec3 position = CORNERS[INDICES[gl_VertexIndex]];
+    ViewProj viewProj = ubo.viewports[pcs.viewportIndex];
+
+    // Only the camera's rotation, so the sky stays infinitely far away.
+    mat4 view = mat4(mat3(viewProj.view));
+    vec4 clip = viewProj.proj * view * vec4(position, 1.0);
+
+    // Depth is z / w, so this puts every fragment on the far plane.
+    gl_Position = clip.xyww;
+    fragDirection = position;
+}